@@ -0,0 +1,228 @@
+//Import standard/external libraries
+use json::{object, JsonValue};
+
+//Import source modules
+use crate::cost;
+use crate::input::{self, ElevatorGameInputError};
+use crate::save::{self, ElevatorGameSaveError};
+
+/// # `EnergyUsage` struct
+///
+/// The `EnergyUsage` struct breaks down the energy spent over a span of
+/// time steps, and what it costs, into off-peak and peak hours.
+#[derive(Clone, Default)]
+pub struct EnergyUsage {
+    pub off_peak_energy: f64,
+    pub peak_energy: f64,
+    pub off_peak_cost: f64,
+    pub peak_cost: f64
+}
+
+//Implement the EnergyUsage interface
+impl EnergyUsage {
+    /// Get the total cost of the energy spent
+    pub fn get_total_cost(&self) -> f64 {
+        self.off_peak_cost + self.peak_cost
+    }
+
+    /// Serialize the energy usage into a JSON object
+    pub fn to_json(&self) -> JsonValue {
+        object!{
+            off_peak_energy: self.off_peak_energy,
+            peak_energy: self.peak_energy,
+            off_peak_cost: self.off_peak_cost,
+            peak_cost: self.peak_cost,
+            total_cost: self.get_total_cost()
+        }
+    }
+
+    /// Initialize an `EnergyUsage` struct from a JSON object produced
+    /// by `to_json`
+    pub fn from_json(value: &JsonValue) -> Result<EnergyUsage, ElevatorGameSaveError> {
+        Ok(EnergyUsage {
+            off_peak_energy: save::get_f64(value, "off_peak_energy")?,
+            peak_energy: save::get_f64(value, "peak_energy")?,
+            off_peak_cost: save::get_f64(value, "off_peak_cost")?,
+            peak_cost: save::get_f64(value, "peak_cost")?
+        })
+    }
+}
+
+/// # `ElectricityBill` struct
+///
+/// The `ElectricityBill` struct prices the energy a building's
+/// elevators spend and bills it periodically.  Energy is priced per
+/// unit, with a higher rate during the peak hours of each day, and the
+/// energy spent since the last bill is billed at the end of every
+/// billing period.
+#[derive(Clone)]
+pub struct ElectricityBill {
+    pub price: f64,
+    pub peak_rate: f64,
+    pub day_length: usize,
+    pub peak_start: usize,
+    pub peak_end: usize,
+    pub billing_period: usize,
+    pub pending: EnergyUsage,
+    pub last_bill: Option<EnergyUsage>,
+    pub total_billed: f64
+}
+
+//Implement the ElectricityBill interface
+impl ElectricityBill {
+    /// Initialize the `ElectricityBill` describing the default
+    /// electricity tariff, with a day of 1000 time steps whose middle
+    /// third is billed at twice the price, billed every 100 time steps
+    pub fn new() -> ElectricityBill {
+        ElectricityBill {
+            price: 0.01_f64,
+            peak_rate: 2.0_f64,
+            day_length: 1000_usize,
+            peak_start: 333_usize,
+            peak_end: 667_usize,
+            billing_period: 100_usize,
+            pending: EnergyUsage::default(),
+            last_bill: None,
+            total_billed: 0.0_f64
+        }
+    }
+
+    /// Initialize an `ElectricityBill` given a JSON electricity config
+    /// object.  Fields which are left out fall back to the default
+    /// tariff.
+    ///
+    /// ## Example
+    ///
+    /// ```json
+    /// {
+    ///     "price": 0.02,
+    ///     "peak_rate": 3.0,
+    ///     "day_length": 500,
+    ///     "peak_start": 200,
+    ///     "peak_end": 300,
+    ///     "billing_period": 50
+    /// }
+    /// ```
+    pub fn from_json(config: &JsonValue) -> Result<ElectricityBill, ElevatorGameInputError> {
+        //Read each field, falling back to the default tariff
+        let default: ElectricityBill = ElectricityBill::new();
+        let bill: ElectricityBill = ElectricityBill {
+            price: cost::get_price(config, "price", Some(default.price))?,
+            peak_rate: cost::get_price(config, "peak_rate", Some(default.peak_rate))?,
+            day_length: input::get_usize(config, "day_length", Some(default.day_length))?,
+            peak_start: input::get_usize(config, "peak_start", Some(default.peak_start))?,
+            peak_end: input::get_usize(config, "peak_end", Some(default.peak_end))?,
+            billing_period: input::get_usize(config, "billing_period", Some(default.billing_period))?,
+            ..default
+        };
+
+        //Make sure the day and billing period are not empty, and the
+        //peak hours fall within the day
+        if bill.day_length == 0_usize {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "day_length".to_string(),
+                value: bill.day_length.to_string()
+            });
+        }
+        if bill.billing_period == 0_usize {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "billing_period".to_string(),
+                value: bill.billing_period.to_string()
+            });
+        }
+        if bill.peak_start > bill.peak_end || bill.peak_end > bill.day_length {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "peak_end".to_string(),
+                value: bill.peak_end.to_string()
+            });
+        }
+        Ok(bill)
+    }
+
+    /// Check whether the given time step falls within peak hours
+    pub fn is_peak(&self, time_step: i32) -> bool {
+        let time_of_day: usize = time_step as usize % self.day_length;
+        time_of_day >= self.peak_start && time_of_day < self.peak_end
+    }
+
+    /// Get the price of a unit of energy at the given time step
+    pub fn get_rate(&self, time_step: i32) -> f64 {
+        if self.is_peak(time_step) {
+            self.price * self.peak_rate
+        } else {
+            self.price
+        }
+    }
+
+    /// Get the number of time steps left before the next bill, counting
+    /// the given time step
+    pub fn get_steps_until_bill(&self, time_step: i32) -> usize {
+        self.billing_period - (time_step as usize % self.billing_period)
+    }
+
+    /// Record the energy spent during the given time step.  If the time
+    /// step ends a billing period, then the energy spent since the last
+    /// bill is billed and the amount due is returned.
+    pub fn record(&mut self, time_step: i32, energy_spent: f64) -> Option<f64> {
+        //Price the energy spent at the current rate
+        let cost: f64 = energy_spent * self.get_rate(time_step);
+        if self.is_peak(time_step) {
+            self.pending.peak_energy += energy_spent;
+            self.pending.peak_cost += cost;
+        } else {
+            self.pending.off_peak_energy += energy_spent;
+            self.pending.off_peak_cost += cost;
+        }
+
+        //Bill the pending usage at the end of the billing period
+        if self.get_steps_until_bill(time_step) > 1_usize {
+            return None;
+        }
+        let bill: EnergyUsage = std::mem::take(&mut self.pending);
+        let amount_due: f64 = bill.get_total_cost();
+        self.total_billed += amount_due;
+        self.last_bill = Some(bill);
+        Some(amount_due)
+    }
+
+    /// Serialize the tariff and the running bill into a JSON object for
+    /// the game state, as of the given time step
+    pub fn to_json(&self, time_step: i32) -> JsonValue {
+        object!{
+            price: self.price,
+            peak_rate: self.peak_rate,
+            is_peak: self.is_peak(time_step),
+            rate: self.get_rate(time_step),
+            billing_period: self.billing_period,
+            steps_until_bill: self.get_steps_until_bill(time_step),
+            pending: self.pending.to_json(),
+            last_bill: self.last_bill.as_ref().map(|bill| bill.to_json()),
+            total_billed: self.total_billed
+        }
+    }
+
+    /// Serialize the running bill into a JSON object for a save.  The
+    /// tariff comes from the game config, so it is not saved.
+    pub fn save_to_json(&self) -> JsonValue {
+        object!{
+            pending: self.pending.to_json(),
+            last_bill: self.last_bill.as_ref().map(|bill| bill.to_json()),
+            total_billed: self.total_billed
+        }
+    }
+
+    /// Restore the running bill from a JSON object produced by
+    /// `save_to_json`, returning the restored bill
+    pub fn restore_from_json(&self, value: &JsonValue) -> Result<ElectricityBill, ElevatorGameSaveError> {
+        let last_bill: Option<EnergyUsage> = match save::get(value, "last_bill")? {
+            JsonValue::Null => None,
+            bill => Some(EnergyUsage::from_json(bill)?)
+        };
+        Ok(ElectricityBill {
+            pending: EnergyUsage::from_json(save::get(value, "pending")?)?,
+            last_bill: last_bill,
+            total_billed: save::get_f64(value, "total_billed")?,
+            ..self.clone()
+        })
+    }
+}
\ No newline at end of file
@@ -0,0 +1,311 @@
+//Import standard/external libraries
+use json::JsonValue;
+use rand::Rng;
+use elevate_lib::building::Building;
+
+//Import source modules
+use crate::billing::ElectricityBill;
+use crate::controller::ControllerKind;
+use crate::game::ElevatorGame;
+use crate::input::{self, ElevatorGameInputError};
+use crate::offline::OfflineProgress;
+use crate::cost::{self, CostCurve};
+use crate::upgrade::{self, ElevatorGameUpgrade, ElevatorGameUpgrades, Prerequisite, UpgradeDefinition};
+
+/// # `BaseStats` struct
+///
+/// The `BaseStats` struct holds the stats of a floor or elevator before
+/// any upgrades, along with the number of extra time steps an elevator
+/// holds its doors open after stopping.
+#[derive(Clone)]
+pub struct BaseStats {
+    pub floor_capacity: usize,
+    pub elevator_capacity: usize,
+    pub energy_up: f64,
+    pub energy_down: f64,
+    pub energy_coef: f64,
+    pub dwell_time: usize
+}
+
+/// # `ElevatorGameConfig` struct
+///
+/// The `ElevatorGameConfig` struct describes the initial state of an
+/// `ElevatorGame`, including its starting building, the pricing of its
+/// upgrades, its electricity tariff, how it catches up on time spent
+/// away, its RNG seed and its elevator controller.  It lets game
+/// designers define difficulty presets and levels as JSON.
+#[derive(Clone)]
+pub struct ElevatorGameConfig {
+    pub num_floors: usize,
+    pub num_elevators: usize,
+    pub p_in: f64,
+    pub floor_capacity: usize,
+    pub lobby_capacity: usize,
+    pub elevator_capacity: usize,
+    pub energy_up: f64,
+    pub energy_down: f64,
+    pub energy_coef: f64,
+    pub dwell_time: usize,
+    pub electricity: ElectricityBill,
+    pub offline: OfflineProgress,
+    pub upgrades: Vec<UpgradeDefinition>,
+    pub seed: Option<u64>,
+    pub controller: ControllerKind
+}
+
+//Implement the ElevatorGameConfig interface
+impl ElevatorGameConfig {
+    /// Initialize the `ElevatorGameConfig` describing the default
+    /// starting game
+    pub fn new() -> ElevatorGameConfig {
+        ElevatorGameConfig {
+            num_floors: 4_usize,
+            num_elevators: 2_usize,
+            p_in: 0.5_f64,
+            floor_capacity: 100_usize,
+            lobby_capacity: 300_usize,
+            elevator_capacity: 10_usize,
+            energy_up: 5.0_f64,
+            energy_down: 2.5_f64,
+            energy_coef: 0.5_f64,
+            dwell_time: 1_usize,
+            electricity: ElectricityBill::new(),
+            offline: OfflineProgress::new(),
+            upgrades: UpgradeDefinition::defaults(),
+            seed: None,
+            controller: ControllerKind::Random
+        }
+    }
+
+    /// Initialize an `ElevatorGameConfig` given a JSON serialized
+    /// string containing a config object.  Fields which are left out
+    /// of the config object fall back to the default game.  The
+    /// `upgrades` may either override the pricing of the default
+    /// upgrades, or be an array of upgrade definitions replacing them.
+    ///
+    /// ## Example
+    ///
+    /// ```json
+    /// {
+    ///     "building": { "num_floors": 6, "num_elevators": 1, "p_in": 0.8, "dwell_time": 1 },
+    ///     "upgrades": {
+    ///         "append_floor": { "base_cost": 25.0, "base_coef": 1.7 },
+    ///         "add_floor_capacity": { "amount": 250 },
+    ///         "look_controller": {
+    ///             "price": { "type": "fixed", "cost": 800.0 },
+    ///             "prerequisites": [{ "type": "min_elevators", "count": 2 }]
+    ///         }
+    ///     },
+    ///     "electricity": { "price": 0.02, "billing_period": 50 },
+    ///     "offline": { "steps_per_second": 4.0, "max_steps": 10000 },
+    ///     "seed": "20240317",
+    ///     "controller": "look"
+    /// }
+    /// ```
+    pub fn from_json(config: String) -> Result<ElevatorGameConfig, ElevatorGameInputError> {
+        //Parse the config string and make sure it is an object
+        let config_object: JsonValue = json::parse(&config)?;
+        if !config_object.is_object() {
+            return Err(ElevatorGameInputError::WrongType {
+                key: "config".to_string(),
+                expected: "object".to_string()
+            });
+        }
+
+        //Read each config field, falling back to the default game
+        let default: ElevatorGameConfig = ElevatorGameConfig::new();
+        let building: &JsonValue = &config_object["building"];
+        let upgrades: &JsonValue = &config_object["upgrades"];
+        let mut config: ElevatorGameConfig = ElevatorGameConfig {
+            num_floors: input::get_usize(building, "num_floors", Some(default.num_floors))?,
+            num_elevators: input::get_usize(building, "num_elevators", Some(default.num_elevators))?,
+            p_in: input::get_f64(building, "p_in", Some(default.p_in))?,
+            floor_capacity: input::get_usize(building, "floor_capacity", Some(default.floor_capacity))?,
+            lobby_capacity: input::get_usize(building, "lobby_capacity", Some(default.lobby_capacity))?,
+            elevator_capacity: input::get_usize(building, "elevator_capacity", Some(default.elevator_capacity))?,
+            energy_up: input::get_f64(building, "energy_up", Some(default.energy_up))?,
+            energy_down: input::get_f64(building, "energy_down", Some(default.energy_down))?,
+            energy_coef: input::get_f64(building, "energy_coef", Some(default.energy_coef))?,
+            dwell_time: input::get_usize(building, "dwell_time", Some(default.dwell_time))?,
+            electricity: ElectricityBill::from_json(&config_object["electricity"])?,
+            offline: OfflineProgress::from_json(&config_object["offline"])?,
+            upgrades: get_upgrade_definitions(upgrades, default.upgrades)?,
+            seed: None,
+            controller: default.controller
+        };
+
+        //Read the seed if one was given, either as a number or as a
+        //string since JS numbers cannot hold every u64
+        let seed: &JsonValue = &config_object["seed"];
+        if !seed.is_null() {
+            let parsed_seed: Option<u64> = match seed.as_str() {
+                Some(seed_str) => seed_str.parse::<u64>().ok(),
+                None => seed.as_u64()
+            };
+            config.seed = Some(parsed_seed.ok_or(ElevatorGameInputError::WrongType {
+                key: "seed".to_string(),
+                expected: "unsigned integer".to_string()
+            })?);
+        }
+
+        //Make sure the building has at least one floor and elevator
+        if config.num_floors == 0_usize {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "num_floors".to_string(),
+                value: config.num_floors.to_string()
+            });
+        }
+        if config.num_elevators == 0_usize {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "num_elevators".to_string(),
+                value: config.num_elevators.to_string()
+            });
+        }
+
+        //Make sure people arrive at a sensible rate, since elevate-lib
+        //panics on an arrival rate which is not positive
+        if !config.p_in.is_finite() || config.p_in <= 0.0_f64 {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "p_in".to_string(),
+                value: config.p_in.to_string()
+            });
+        }
+
+        //Make sure moving the elevators costs a sensible amount of energy
+        for (key, value) in [
+            ("energy_up", config.energy_up),
+            ("energy_down", config.energy_down),
+            ("energy_coef", config.energy_coef)
+        ] {
+            if !value.is_finite() || value < 0.0_f64 {
+                return Err(ElevatorGameInputError::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string()
+                });
+            }
+        }
+
+        //Read the controller, making sure it is one the game can build
+        let controller: &str = input::get_str(&config_object, "controller", Some(default.controller.as_str()))?;
+        config.controller = ControllerKind::from_str(controller).ok_or(ElevatorGameInputError::InvalidValue {
+            key: "controller".to_string(),
+            value: controller.to_string()
+        })?;
+
+        //Return the config
+        Ok(config)
+    }
+
+    /// Initialize the `ElevatorGame` described by the config
+    pub fn build(&self) -> ElevatorGame {
+        //Use the configured seed, or draw one if unseeded so that the
+        //game can still be reproduced from its reported seed
+        let seed: u64 = match self.seed {
+            Some(seed) => seed,
+            None => rand::thread_rng().gen()
+        };
+        self.build_with_seed(seed)
+    }
+
+    /// Initialize the `ElevatorGame` described by the config, seeded
+    /// with the given seed in place of the configured one
+    pub fn build_with_seed(&self, seed: u64) -> ElevatorGame {
+        //Initialize the starting building, giving the lobby its own
+        //capacity since everyone enters and leaves through it
+        let mut building: Building = Building::from(
+            self.num_floors,
+            self.num_elevators,
+            self.p_in,
+            self.floor_capacity,
+            self.elevator_capacity,
+            self.energy_up,
+            self.energy_down,
+            self.energy_coef
+        );
+        building.floors[0].capacity = self.lobby_capacity;
+
+        //Initialize the upgrades, counting the starting controller as
+        //owned
+        let mut upgrades: ElevatorGameUpgrades = ElevatorGameUpgrades::from(self.upgrades.clone());
+        if let Some(upgrade) = upgrades.get_controller_mut(self.controller) {
+            upgrade.set_num_buys(1_usize);
+        }
+
+        //Initialize and return the game
+        ElevatorGame::from(self.controller, building, self.p_in, upgrades, self.electricity.clone(), self.get_base_stats(), seed)
+    }
+
+    /// Get the stats of the configured floors and elevators before any
+    /// upgrades
+    pub fn get_base_stats(&self) -> BaseStats {
+        BaseStats {
+            floor_capacity: self.floor_capacity,
+            elevator_capacity: self.elevator_capacity,
+            energy_up: self.energy_up,
+            energy_down: self.energy_down,
+            energy_coef: self.energy_coef,
+            dwell_time: self.dwell_time
+        }
+    }
+}
+
+/// Get the upgrade definitions from the upgrades config.  An array
+/// replaces the whole upgrade registry, while an object keyed by
+/// upgrade id overrides the pricing of the default upgrades, either
+/// with a `price` object or, for curves which have them, with a
+/// `base_cost` and `base_coef`.  It may also override the `amount` or
+/// `factor` of their effect and replace their `prerequisites`.
+fn get_upgrade_definitions(upgrades: &JsonValue, defaults: Vec<UpgradeDefinition>) -> Result<Vec<UpgradeDefinition>, ElevatorGameInputError> {
+    //Replace the registry if given an array of definitions
+    if upgrades.is_array() {
+        return UpgradeDefinition::from_json_array(upgrades);
+    }
+
+    //Otherwise override the pricing and prerequisites of the defaults
+    let mut definitions: Vec<UpgradeDefinition> = defaults;
+    for (id, pricing) in upgrades.entries() {
+        //Find the overridden upgrade
+        let definition: &mut UpgradeDefinition = definitions.iter_mut()
+            .find(|definition| definition.id == id)
+            .ok_or(ElevatorGameInputError::InvalidValue {
+                key: "upgrades".to_string(),
+                value: id.to_string()
+            })?;
+
+        //Override its price
+        if !pricing["price"].is_null() {
+            definition.price = CostCurve::from_json(&pricing["price"])?;
+        } else {
+            match definition.price {
+                CostCurve::BasePlusPower { base_cost, base_coef } => {
+                    definition.price = CostCurve::BasePlusPower {
+                        base_cost: cost::get_price(pricing, "base_cost", Some(base_cost))?,
+                        base_coef: cost::get_price(pricing, "base_coef", Some(base_coef))?
+                    };
+                },
+                CostCurve::Exponential { base_cost, base_coef } => {
+                    definition.price = CostCurve::Exponential {
+                        base_cost: cost::get_price(pricing, "base_cost", Some(base_cost))?,
+                        base_coef: cost::get_price(pricing, "base_coef", Some(base_coef))?
+                    };
+                },
+                _ => {}
+            }
+        }
+
+        //Override the magnitude of its effect
+        if !pricing["amount"].is_null() {
+            definition.effect = definition.effect.with_amount(input::get_usize(pricing, "amount", None)?);
+        }
+        if !pricing["factor"].is_null() {
+            definition.effect = definition.effect.with_factor(upgrade::get_factor(pricing)?);
+        }
+
+        //Override its prerequisites
+        if !pricing["prerequisites"].is_null() {
+            definition.prerequisites = Prerequisite::from_json_array(&pricing["prerequisites"])?;
+        }
+    }
+    UpgradeDefinition::check_prerequisites(&definitions)?;
+    Ok(definitions)
+}
\ No newline at end of file
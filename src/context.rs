@@ -0,0 +1,32 @@
+//Import source modules
+use crate::config::BaseStats;
+use crate::controller::ControllerKind;
+use crate::purchase::PurchaseTarget;
+
+/// # `GameContext` struct
+///
+/// The `GameContext` struct holds the parts of an `ElevatorGame` which
+/// live outside of its building and which upgrades may change when
+/// they are applied, along with what the upgrade was bought for and
+/// the stats of any floors or elevators the upgrade builds.  The
+/// game reads the context back once the upgrade has been applied,
+/// switching controllers if the controller kind was changed.
+pub struct GameContext {
+    pub tips: f64,
+    pub controller_kind: ControllerKind,
+    pub target: PurchaseTarget,
+    pub new_stats: BaseStats
+}
+
+//Implement the GameContext interface
+impl GameContext {
+    /// Initialize a `GameContext` struct explicitly
+    pub fn new(tips: f64, controller_kind: ControllerKind, target: PurchaseTarget, new_stats: BaseStats) -> GameContext {
+        GameContext {
+            tips: tips,
+            controller_kind: controller_kind,
+            target: target,
+            new_stats: new_stats
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,483 @@
+//Import standard/external libraries
+use rand::rngs::StdRng;
+use json::{object, JsonValue};
+use elevate_lib::building::Building;
+use elevate_lib::controller::{ElevatorController, NearestController, RandomController};
+use elevate_lib::elevator::Elevator;
+use elevate_lib::people::People;
+
+//Import source modules
+use crate::save::{self, ElevatorGameSaveError};
+
+/// # `ControllerKind` enum
+///
+/// The `ControllerKind` enum lists the elevator controller strategies
+/// the game can run a building with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ControllerKind {
+    Manual,
+    Random,
+    Nearest,
+    Look,
+    Collective,
+    RoundRobin
+}
+
+//Implement the ControllerKind interface
+impl ControllerKind {
+    /// Every controller kind, in order of increasing sophistication
+    pub const ALL: [ControllerKind; 6] = [
+        ControllerKind::Manual,
+        ControllerKind::Random,
+        ControllerKind::RoundRobin,
+        ControllerKind::Nearest,
+        ControllerKind::Look,
+        ControllerKind::Collective
+    ];
+
+    /// Parse a controller kind from its name
+    pub fn from_str(name: &str) -> Option<ControllerKind> {
+        match name {
+            "manual" => Some(ControllerKind::Manual),
+            "random" => Some(ControllerKind::Random),
+            "nearest" => Some(ControllerKind::Nearest),
+            "look" => Some(ControllerKind::Look),
+            "collective" => Some(ControllerKind::Collective),
+            "round_robin" => Some(ControllerKind::RoundRobin),
+            _ => None
+        }
+    }
+
+    /// Get the name of the controller kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ControllerKind::Manual => "manual",
+            ControllerKind::Random => "random",
+            ControllerKind::Nearest => "nearest",
+            ControllerKind::Look => "look",
+            ControllerKind::Collective => "collective",
+            ControllerKind::RoundRobin => "round_robin"
+        }
+    }
+
+    /// Initialize a controller of this kind for the given building.
+    /// The RNG is only used by the random controller.
+    pub fn build(&self, building: Building, rng: StdRng) -> Box<dyn GameController> {
+        match self {
+            ControllerKind::Manual => Box::new(ManualController::from(building)),
+            ControllerKind::Random => Box::new(RandomController::from(building, rng, 0.0_f64)),
+            ControllerKind::Nearest => Box::new(NearestController::from(building)),
+            ControllerKind::Look => Box::new(LookController::from(building)),
+            ControllerKind::Collective => Box::new(CollectiveController::from(building)),
+            ControllerKind::RoundRobin => Box::new(RoundRobinController::from(building))
+        }
+    }
+}
+
+/// # `GameController` trait
+///
+/// The `GameController` trait extends elevate-lib's `ElevatorController`
+/// with saving and restoring whatever the controller keeps track of
+/// besides its building.  Controllers which keep nothing else save
+/// nothing.
+pub trait GameController: ElevatorController + Send {
+    /// Serialize the state of the controller into a save object
+    fn save_state(&self) -> JsonValue {
+        JsonValue::Null
+    }
+
+    /// Restore the state of the controller from a save object produced
+    /// by `save_state`
+    fn restore_state(&mut self, _state: &JsonValue) -> Result<(), ElevatorGameSaveError> {
+        Ok(())
+    }
+}
+
+/// The random controller's RNG and the floors it chose for each
+/// elevator are kept private by elevate-lib, so they cannot be saved.
+/// A random controller loaded from a save starts afresh.
+impl GameController for RandomController {}
+
+/// The nearest controller keeps nothing besides its building
+impl GameController for NearestController {}
+
+/// Point an elevator towards its destination floor and move it one
+/// floor in that direction, or stop it if it is already there.  The
+/// elevator keeps its direction while stopped so that directional
+/// controllers can resume in the same direction.
+pub fn move_toward(elevator: &mut Elevator, dest_floor: Option<usize>) {
+    match dest_floor {
+        Some(dest_floor) if dest_floor != elevator.floor_on => {
+            elevator.stopped = false;
+            elevator.moving_up = dest_floor > elevator.floor_on;
+        },
+        _ => {
+            elevator.stopped = true;
+        }
+    }
+    elevator.update_floor();
+}
+
+/// Check whether an elevator has no room left for more people
+fn is_full(elevator: &Elevator) -> bool {
+    elevator.get_num_people() >= elevator.capacity
+}
+
+/// Get the floors the people on an elevator are headed to
+fn get_rider_floors(elevator: &Elevator) -> Vec<usize> {
+    elevator.people.iter().map(|person| person.floor_to).collect()
+}
+
+/// Get the floors with people waiting for an elevator.  If a direction
+/// is given, then only floors with people waiting to go in that
+/// direction are returned.
+fn get_call_floors(building: &Building, moving_up: Option<bool>) -> Vec<usize> {
+    let mut call_floors: Vec<usize> = Vec::new();
+    for (floor_index, floor) in building.floors.iter().enumerate() {
+        let is_called: bool = floor.get_dest_floors().iter().any(|floor_to| {
+            *floor_to != floor_index && match moving_up {
+                Some(up) => (*floor_to > floor_index) == up,
+                None => true
+            }
+        });
+        if is_called {
+            call_floors.push(floor_index);
+        }
+    }
+    call_floors
+}
+
+/// Get the floor nearest to the given floor out of a list of floors
+fn get_nearest_floor(floor_on: usize, floors: &[usize]) -> Option<usize> {
+    floors.iter()
+        .copied()
+        .min_by_key(|floor| if *floor > floor_on { floor - floor_on } else { floor_on - floor })
+}
+
+/// Get the next floor a directional (SCAN/LOOK style) elevator should
+/// head to given the floors it is asked to serve.  The elevator keeps
+/// going in its current direction while there are requests ahead of
+/// it, and reverses once there are none.
+fn get_look_floor(elevator: &Elevator, requests: &[usize]) -> Option<usize> {
+    //Find the nearest request ahead of and behind the elevator
+    let floor_on: usize = elevator.floor_on;
+    let ahead: Vec<usize> = requests.iter().copied()
+        .filter(|floor| if elevator.moving_up { *floor > floor_on } else { *floor < floor_on })
+        .collect();
+    let behind: Vec<usize> = requests.iter().copied()
+        .filter(|floor| if elevator.moving_up { *floor < floor_on } else { *floor > floor_on })
+        .collect();
+
+    //Continue ahead if possible, otherwise reverse
+    if !ahead.is_empty() {
+        get_nearest_floor(floor_on, &ahead)
+    } else {
+        get_nearest_floor(floor_on, &behind)
+    }
+}
+
+/// # `ManualController` struct
+///
+/// The `ManualController` leaves the elevators to the player.  It never
+/// moves an elevator on its own, so each elevator waits where it is
+/// until the player dispatches it to a floor.
+pub struct ManualController {
+    pub building: Building
+}
+
+impl ManualController {
+    /// Initialize a new `ManualController` given a `Building`
+    pub fn from(building: Building) -> ManualController {
+        ManualController {
+            building: building
+        }
+    }
+}
+
+impl GameController for ManualController {}
+
+impl ElevatorController for ManualController {
+    /// Get an immutable reference to the building
+    fn get_building(&mut self) -> &Building {
+        &self.building
+    }
+
+    /// Get a mutable reference to the building
+    fn get_building_mut(&mut self) -> &mut Building {
+        &mut self.building
+    }
+
+    /// Clone the building
+    fn clone_building(&mut self) -> Building {
+        self.building.clone()
+    }
+
+    /// Check whether the controller can be upgraded, which it cannot
+    fn can_be_upgraded(&self) -> bool {
+        false
+    }
+
+    /// Upgrade the controller, which does nothing since it cannot be
+    /// upgraded
+    fn upgrade(&mut self, _incrementation: f64) {}
+
+    /// Stop each elevator where it is
+    fn update_elevators(&mut self) {
+        for elevator in self.building.elevators.iter_mut() {
+            move_toward(elevator, None);
+        }
+    }
+}
+
+/// # `LookController` struct
+///
+/// The `LookController` implements the LOOK variant of the SCAN
+/// algorithm.  Each elevator sweeps in one direction serving every
+/// request along the way, and reverses once there are no requests
+/// left ahead of it.
+pub struct LookController {
+    pub building: Building
+}
+
+impl LookController {
+    /// Initialize a new `LookController` given a `Building`
+    pub fn from(building: Building) -> LookController {
+        LookController {
+            building: building
+        }
+    }
+}
+
+impl GameController for LookController {}
+
+impl ElevatorController for LookController {
+    /// Get an immutable reference to the building
+    fn get_building(&mut self) -> &Building {
+        &self.building
+    }
+
+    /// Get a mutable reference to the building
+    fn get_building_mut(&mut self) -> &mut Building {
+        &mut self.building
+    }
+
+    /// Clone the building
+    fn clone_building(&mut self) -> Building {
+        self.building.clone()
+    }
+
+    /// Check whether the controller can be upgraded, which it cannot
+    fn can_be_upgraded(&self) -> bool {
+        false
+    }
+
+    /// Upgrade the controller, which does nothing since it cannot be
+    /// upgraded
+    fn upgrade(&mut self, _incrementation: f64) {}
+
+    /// Sweep each elevator towards the requests in its direction
+    fn update_elevators(&mut self) {
+        let call_floors: Vec<usize> = get_call_floors(&self.building, None);
+        for elevator in self.building.elevators.iter_mut() {
+            //Gather the elevator's requests, ignoring calls if it is full
+            let mut requests: Vec<usize> = get_rider_floors(elevator);
+            if !is_full(elevator) {
+                requests.extend(call_floors.iter());
+            }
+
+            //Stop on a requested floor, otherwise keep sweeping
+            let dest_floor: Option<usize> = if requests.contains(&elevator.floor_on) {
+                Some(elevator.floor_on)
+            } else {
+                get_look_floor(elevator, &requests)
+            };
+            move_toward(elevator, dest_floor);
+        }
+    }
+}
+
+/// # `CollectiveController` struct
+///
+/// The `CollectiveController` implements directional collective
+/// control.  Like the `LookController` each elevator sweeps in one
+/// direction, but it only stops for waiting people who are headed in
+/// its direction of travel, picking the rest up on the way back.
+pub struct CollectiveController {
+    pub building: Building
+}
+
+impl CollectiveController {
+    /// Initialize a new `CollectiveController` given a `Building`
+    pub fn from(building: Building) -> CollectiveController {
+        CollectiveController {
+            building: building
+        }
+    }
+}
+
+impl GameController for CollectiveController {}
+
+impl ElevatorController for CollectiveController {
+    /// Get an immutable reference to the building
+    fn get_building(&mut self) -> &Building {
+        &self.building
+    }
+
+    /// Get a mutable reference to the building
+    fn get_building_mut(&mut self) -> &mut Building {
+        &mut self.building
+    }
+
+    /// Clone the building
+    fn clone_building(&mut self) -> Building {
+        self.building.clone()
+    }
+
+    /// Check whether the controller can be upgraded, which it cannot
+    fn can_be_upgraded(&self) -> bool {
+        false
+    }
+
+    /// Upgrade the controller, which does nothing since it cannot be
+    /// upgraded
+    fn upgrade(&mut self, _incrementation: f64) {}
+
+    /// Sweep each elevator, stopping for calls in its direction
+    fn update_elevators(&mut self) {
+        let call_floors: Vec<usize> = get_call_floors(&self.building, None);
+        let up_call_floors: Vec<usize> = get_call_floors(&self.building, Some(true));
+        let down_call_floors: Vec<usize> = get_call_floors(&self.building, Some(false));
+        for elevator in self.building.elevators.iter_mut() {
+            //Gather the elevator's requests, ignoring calls if it is full
+            let rider_floors: Vec<usize> = get_rider_floors(elevator);
+            let mut requests: Vec<usize> = rider_floors.clone();
+            if !is_full(elevator) {
+                requests.extend(call_floors.iter());
+            }
+
+            //Stop for riders, for calls in the direction of travel, or
+            //for any call if this is where the elevator turns around
+            let next_floor: Option<usize> = get_look_floor(elevator, &requests);
+            let is_turning: bool = match next_floor {
+                Some(next_floor) => (next_floor > elevator.floor_on) != elevator.moving_up,
+                None => true
+            };
+            let directional_calls: &Vec<usize> = if elevator.moving_up { &up_call_floors } else { &down_call_floors };
+            let is_stopping: bool = rider_floors.contains(&elevator.floor_on) || (!is_full(elevator) && (
+                directional_calls.contains(&elevator.floor_on) ||
+                (is_turning && call_floors.contains(&elevator.floor_on))
+            ));
+            let dest_floor: Option<usize> = if is_stopping { Some(elevator.floor_on) } else { next_floor };
+            move_toward(elevator, dest_floor);
+        }
+    }
+}
+
+/// # `RoundRobinController` struct
+///
+/// The `RoundRobinController` assigns each new call to the elevators
+/// in turn.  Each elevator delivers its riders first, then heads to
+/// the call it was assigned.
+pub struct RoundRobinController {
+    pub building: Building,
+    assignments: Vec<Option<usize>>,
+    next_elevator: usize
+}
+
+impl RoundRobinController {
+    /// Initialize a new `RoundRobinController` given a `Building`
+    pub fn from(building: Building) -> RoundRobinController {
+        let num_elevators: usize = building.elevators.len();
+        RoundRobinController {
+            building: building,
+            assignments: vec![None; num_elevators],
+            next_elevator: 0_usize
+        }
+    }
+}
+
+impl GameController for RoundRobinController {
+    /// Serialize the call each elevator was assigned and the elevator
+    /// next in turn into a save object
+    fn save_state(&self) -> JsonValue {
+        object!{
+            assignments: self.assignments.clone(),
+            next_elevator: self.next_elevator
+        }
+    }
+
+    /// Restore the call each elevator was assigned and the elevator next
+    /// in turn from a save object
+    fn restore_state(&mut self, state: &JsonValue) -> Result<(), ElevatorGameSaveError> {
+        self.assignments = save::get_optional_usize_array(state, "assignments")?;
+        self.next_elevator = save::get_usize(state, "next_elevator")?;
+        Ok(())
+    }
+}
+
+impl ElevatorController for RoundRobinController {
+    /// Get an immutable reference to the building
+    fn get_building(&mut self) -> &Building {
+        &self.building
+    }
+
+    /// Get a mutable reference to the building
+    fn get_building_mut(&mut self) -> &mut Building {
+        &mut self.building
+    }
+
+    /// Clone the building
+    fn clone_building(&mut self) -> Building {
+        self.building.clone()
+    }
+
+    /// Check whether the controller can be upgraded, which it cannot
+    fn can_be_upgraded(&self) -> bool {
+        false
+    }
+
+    /// Upgrade the controller, which does nothing since it cannot be
+    /// upgraded
+    fn upgrade(&mut self, _incrementation: f64) {}
+
+    /// Assign new calls in turn and move each elevator accordingly
+    fn update_elevators(&mut self) {
+        //Keep an assignment slot for each elevator, including new ones
+        let num_elevators: usize = self.building.elevators.len();
+        self.assignments.resize(num_elevators, None);
+
+        //Drop assignments to floors which are no longer calling
+        let call_floors: Vec<usize> = get_call_floors(&self.building, None);
+        for assignment in self.assignments.iter_mut() {
+            if assignment.is_some_and(|floor| !call_floors.contains(&floor)) {
+                *assignment = None;
+            }
+        }
+
+        //Assign each unassigned call to the next elevator in turn
+        for call_floor in call_floors.iter() {
+            if self.assignments.contains(&Some(*call_floor)) {
+                continue;
+            }
+            for _ in 0..num_elevators {
+                let elevator_index: usize = self.next_elevator % num_elevators;
+                self.next_elevator = (elevator_index + 1_usize) % num_elevators;
+                if self.assignments[elevator_index].is_none() && !is_full(&self.building.elevators[elevator_index]) {
+                    self.assignments[elevator_index] = Some(*call_floor);
+                    break;
+                }
+            }
+        }
+
+        //Move each elevator to its riders' floors, then its assigned call
+        for (elevator, assignment) in self.building.elevators.iter_mut().zip(self.assignments.iter()) {
+            let rider_floors: Vec<usize> = get_rider_floors(elevator);
+            let dest_floor: Option<usize> = if !rider_floors.is_empty() {
+                get_nearest_floor(elevator.floor_on, &rider_floors)
+            } else {
+                *assignment
+            };
+            move_toward(elevator, dest_floor);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,242 @@
+//Import standard/external libraries
+use json::JsonValue;
+
+//Import source modules
+use crate::input::{self, ElevatorGameInputError};
+
+/// # `CostCurve` enum
+///
+/// The `CostCurve` enum describes how an upgrade prices itself given
+/// the number of times it has already been bought, `n`.
+///
+/// - `Free`: always `0`
+/// - `Fixed`: always `cost`
+/// - `BasePlusPower`: `base_cost + base_coef^n`
+/// - `Linear`: `base_cost + step * n`
+/// - `Exponential`: `base_cost * base_coef^n`
+/// - `Polynomial`: `base_cost + coef * n^power`
+/// - `Step`: the `n`th entry of a table of costs, repeating the last
+///   entry once the table runs out
+/// - `Piecewise`: a list of segments, each starting at a number of
+///   buys and priced by its own curve counting from its start
+#[derive(Clone, PartialEq, Debug)]
+pub enum CostCurve {
+    Free,
+    Fixed(f64),
+    BasePlusPower { base_cost: f64, base_coef: f64 },
+    Linear { base_cost: f64, step: f64 },
+    Exponential { base_cost: f64, base_coef: f64 },
+    Polynomial { base_cost: f64, coef: f64, power: f64 },
+    Step(Vec<f64>),
+    Piecewise(Vec<(usize, CostCurve)>)
+}
+
+//Implement the CostCurve interface
+impl CostCurve {
+    /// Get the price of the next buy given the number of buys so far
+    pub fn get_cost(&self, num_buys: usize) -> f64 {
+        let n: f64 = num_buys as f64;
+        match self {
+            CostCurve::Free => 0.0_f64,
+            CostCurve::Fixed(cost) => *cost,
+            CostCurve::BasePlusPower { base_cost, base_coef } => base_cost + f64::powf(*base_coef, n),
+            CostCurve::Linear { base_cost, step } => base_cost + step * n,
+            CostCurve::Exponential { base_cost, base_coef } => base_cost * f64::powf(*base_coef, n),
+            CostCurve::Polynomial { base_cost, coef, power } => base_cost + coef * f64::powf(n, *power),
+            CostCurve::Step(costs) => costs.get(num_buys).or(costs.last()).copied().unwrap_or(0.0_f64),
+            CostCurve::Piecewise(segments) => {
+                //Price the buy by the last segment starting at or before it
+                let (start, curve) = segments.iter()
+                    .rev()
+                    .find(|(start, _)| *start <= num_buys)
+                    .unwrap_or(&segments[0]);
+                curve.get_cost(num_buys.saturating_sub(*start))
+            }
+        }
+    }
+
+    /// Parse a cost curve from a JSON price object.  Every number in it
+    /// must be finite and non-negative.
+    ///
+    /// ## Example
+    ///
+    /// ```json
+    /// {
+    ///     "type": "piecewise",
+    ///     "segments": [
+    ///         { "from": 0, "price": { "type": "step", "costs": [5.0, 10.0, 20.0] } },
+    ///         { "from": 3, "price": { "type": "exponential", "base_cost": 40.0, "base_coef": 1.15 } }
+    ///     ]
+    /// }
+    /// ```
+    pub fn from_json(price: &JsonValue) -> Result<CostCurve, ElevatorGameInputError> {
+        let price_type: &str = input::get_str(price, "type", None)?;
+        match price_type {
+            "free" => Ok(CostCurve::Free),
+            "fixed" => Ok(CostCurve::Fixed(get_price(price, "cost", None)?)),
+            "base_plus_power" => Ok(CostCurve::BasePlusPower {
+                base_cost: get_price(price, "base_cost", None)?,
+                base_coef: get_price(price, "base_coef", None)?
+            }),
+            "linear" => Ok(CostCurve::Linear {
+                base_cost: get_price(price, "base_cost", None)?,
+                step: get_price(price, "step", None)?
+            }),
+            "exponential" => Ok(CostCurve::Exponential {
+                base_cost: get_price(price, "base_cost", None)?,
+                base_coef: get_price(price, "base_coef", None)?
+            }),
+            "polynomial" => Ok(CostCurve::Polynomial {
+                base_cost: get_price(price, "base_cost", None)?,
+                coef: get_price(price, "coef", None)?,
+                power: get_price(price, "power", None)?
+            }),
+            "step" => {
+                //Read the table of costs, which must not be empty
+                if price["costs"].is_null() {
+                    return Err(ElevatorGameInputError::MissingKey("costs".to_string()));
+                }
+                let mut costs: Vec<f64> = Vec::new();
+                for cost in price["costs"].members() {
+                    let cost: f64 = cost.as_f64().ok_or(ElevatorGameInputError::WrongType {
+                        key: "costs".to_string(),
+                        expected: "number".to_string()
+                    })?;
+                    costs.push(check_price("costs", cost)?);
+                }
+                if costs.is_empty() {
+                    return Err(ElevatorGameInputError::InvalidValue {
+                        key: "costs".to_string(),
+                        value: "[]".to_string()
+                    });
+                }
+                Ok(CostCurve::Step(costs))
+            },
+            "piecewise" => {
+                //Read each segment, which must start in increasing order
+                //with the first starting at zero buys
+                let mut segments: Vec<(usize, CostCurve)> = Vec::new();
+                for segment in price["segments"].members() {
+                    let start: usize = input::get_usize(segment, "from", None)?;
+                    let is_ordered: bool = match segments.last() {
+                        Some((last_start, _)) => start > *last_start,
+                        None => start == 0_usize
+                    };
+                    if !is_ordered {
+                        return Err(ElevatorGameInputError::InvalidValue {
+                            key: "from".to_string(),
+                            value: start.to_string()
+                        });
+                    }
+                    segments.push((start, CostCurve::from_json(&segment["price"])?));
+                }
+                if segments.is_empty() {
+                    return Err(ElevatorGameInputError::MissingKey("segments".to_string()));
+                }
+                Ok(CostCurve::Piecewise(segments))
+            },
+            _ => Err(ElevatorGameInputError::InvalidValue {
+                key: "type".to_string(),
+                value: price_type.to_string()
+            })
+        }
+    }
+}
+
+/// Get a price by key from a JSON price object, making sure it is
+/// finite and non-negative.  Falls back to the default if given one
+/// and the key is missing.
+pub fn get_price(price: &JsonValue, key: &str, default: Option<f64>) -> Result<f64, ElevatorGameInputError> {
+    check_price(key, input::get_f64(price, key, default)?)
+}
+
+/// Make sure a price read from the given key is finite and non-negative
+fn check_price(key: &str, value: f64) -> Result<f64, ElevatorGameInputError> {
+    if !value.is_finite() || value < 0.0_f64 {
+        return Err(ElevatorGameInputError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string()
+        });
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_repeats_its_last_cost() {
+        let curve: CostCurve = CostCurve::Step(vec![5.0_f64, 10.0_f64, 20.0_f64]);
+        assert_eq!(curve.get_cost(0_usize), 5.0_f64);
+        assert_eq!(curve.get_cost(2_usize), 20.0_f64);
+        assert_eq!(curve.get_cost(3_usize), 20.0_f64);
+        assert_eq!(curve.get_cost(usize::MAX), 20.0_f64);
+    }
+
+    #[test]
+    fn step_needs_a_cost() {
+        let empty: JsonValue = json::parse(r#"{ "type": "step", "costs": [] }"#).unwrap();
+        let missing: JsonValue = json::parse(r#"{ "type": "step" }"#).unwrap();
+        assert!(matches!(CostCurve::from_json(&empty), Err(ElevatorGameInputError::InvalidValue { .. })));
+        assert!(matches!(CostCurve::from_json(&missing), Err(ElevatorGameInputError::MissingKey(_))));
+        assert_eq!(CostCurve::Step(Vec::new()).get_cost(3_usize), 0.0_f64);
+    }
+
+    #[test]
+    fn prices_must_be_non_negative() {
+        let negative: JsonValue = json::parse(r#"{ "type": "fixed", "cost": -1.0 }"#).unwrap();
+        let negative_step: JsonValue = json::parse(r#"{ "type": "step", "costs": [1.0, -2.0] }"#).unwrap();
+        let negative_coef: JsonValue = json::parse(r#"{ "type": "exponential", "base_cost": 1.0, "base_coef": -1.1 }"#).unwrap();
+        assert!(matches!(CostCurve::from_json(&negative), Err(ElevatorGameInputError::InvalidValue { .. })));
+        assert!(matches!(CostCurve::from_json(&negative_step), Err(ElevatorGameInputError::InvalidValue { .. })));
+        assert!(matches!(CostCurve::from_json(&negative_coef), Err(ElevatorGameInputError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn piecewise_counts_from_each_segment_start() {
+        let curve: CostCurve = CostCurve::Piecewise(vec![
+            (0_usize, CostCurve::Step(vec![5.0_f64, 10.0_f64, 20.0_f64])),
+            (3_usize, CostCurve::Exponential { base_cost: 40.0_f64, base_coef: 2.0_f64 })
+        ]);
+        assert_eq!(curve.get_cost(0_usize), 5.0_f64);
+        assert_eq!(curve.get_cost(2_usize), 20.0_f64);
+        assert_eq!(curve.get_cost(3_usize), 40.0_f64);
+        assert_eq!(curve.get_cost(5_usize), 160.0_f64);
+    }
+
+    #[test]
+    fn piecewise_segments_start_at_zero_in_increasing_order() {
+        let not_from_zero: JsonValue = json::parse(r#"{
+            "type": "piecewise",
+            "segments": [{ "from": 1, "price": { "type": "fixed", "cost": 1.0 } }]
+        }"#).unwrap();
+        let out_of_order: JsonValue = json::parse(r#"{
+            "type": "piecewise",
+            "segments": [
+                { "from": 0, "price": { "type": "fixed", "cost": 1.0 } },
+                { "from": 0, "price": { "type": "fixed", "cost": 2.0 } }
+            ]
+        }"#).unwrap();
+        let empty: JsonValue = json::parse(r#"{ "type": "piecewise", "segments": [] }"#).unwrap();
+        assert!(matches!(CostCurve::from_json(&not_from_zero), Err(ElevatorGameInputError::InvalidValue { .. })));
+        assert!(matches!(CostCurve::from_json(&out_of_order), Err(ElevatorGameInputError::InvalidValue { .. })));
+        assert!(matches!(CostCurve::from_json(&empty), Err(ElevatorGameInputError::MissingKey(_))));
+    }
+
+    #[test]
+    fn piecewise_parses_nested_curves() {
+        let price: JsonValue = json::parse(r#"{
+            "type": "piecewise",
+            "segments": [
+                { "from": 0, "price": { "type": "step", "costs": [5.0, 10.0, 20.0] } },
+                { "from": 3, "price": { "type": "exponential", "base_cost": 40.0, "base_coef": 1.15 } }
+            ]
+        }"#).unwrap();
+        let expected: CostCurve = CostCurve::Piecewise(vec![
+            (0_usize, CostCurve::Step(vec![5.0_f64, 10.0_f64, 20.0_f64])),
+            (3_usize, CostCurve::Exponential { base_cost: 40.0_f64, base_coef: 1.15_f64 })
+        ]);
+        assert_eq!(CostCurve::from_json(&price).unwrap(), expected);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,170 @@
+//Import standard/external libraries
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use json::JsonValue;
+
+/// The most purchase outcomes remembered for readers of the game state
+/// which have not caught up on them yet.  Older outcomes are forgotten.
+pub const MAX_PURCHASE_HISTORY: usize = 256_usize;
+
+/// Fingerprint a value, so that a change to it can be spotted without
+/// keeping a copy of it around
+pub fn fingerprint<T: Hash>(value: &T) -> u64 {
+    let mut hasher: DefaultHasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// # `TrackedEntries` struct
+///
+/// The `TrackedEntries` struct remembers the fingerprint of each entry
+/// of one part of the game state, by floor, elevator, upgrade or field
+/// index, along with the revision it last changed in.
+#[derive(Default)]
+struct TrackedEntries {
+    entries: Vec<(u64, u32)>
+}
+
+//Implement the TrackedEntries interface
+impl TrackedEntries {
+    /// Record the fingerprint of each entry, marking the entries which
+    /// are new or whose fingerprint changed as changed in the given
+    /// revision and forgetting entries which are gone
+    fn mark(&mut self, fingerprints: Vec<u64>, revision: u32) {
+        self.entries.truncate(fingerprints.len());
+        for (index, fingerprint) in fingerprints.into_iter().enumerate() {
+            match self.entries.get_mut(index) {
+                Some(entry) => if entry.0 != fingerprint {
+                    *entry = (fingerprint, revision);
+                },
+                None => self.entries.push((fingerprint, revision))
+            }
+        }
+    }
+
+    /// Get the indices of the entries which changed after the given
+    /// revision
+    fn changed_since(&self, since: u32) -> Vec<usize> {
+        self.entries.iter()
+            .enumerate()
+            .filter(|(_, (_, changed_in))| *changed_in > since)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// # `StateDiffTracker` struct
+///
+/// The `StateDiffTracker` struct keeps track of which floors,
+/// elevators, upgrades and scalar fields of the game state changed in
+/// which revision, so that the front-end only receives what it does not
+/// already hold.  Each time a diff is read, the game marks what changed
+/// by fingerprint and then numbers a new revision, so everything which
+/// changed since the last diff belongs to the new revision.  It also keeps the outcome of each purchase along with
+/// the revision it belongs to, read separately by the game state and
+/// by diffs so that neither takes them from the other.
+#[derive(Default)]
+pub struct StateDiffTracker {
+    revision: u32,
+    floors: TrackedEntries,
+    elevators: TrackedEntries,
+    upgrades: TrackedEntries,
+    scalars: TrackedEntries,
+    purchases: VecDeque<(u32, JsonValue)>,
+    num_purchases: usize,
+    num_purchases_reported: usize
+}
+
+//Implement the StateDiffTracker interface
+impl StateDiffTracker {
+    /// Initialize a `StateDiffTracker` which has not been shown any
+    /// game state yet
+    pub fn new() -> StateDiffTracker {
+        StateDiffTracker::default()
+    }
+
+    /// Record the fingerprints of the floors, elevators, upgrades and
+    /// scalar fields of the game state, marking those which changed as
+    /// changed in the next revision
+    pub fn mark(&mut self, floors: Vec<u64>, elevators: Vec<u64>, upgrades: Vec<u64>, scalars: Vec<u64>) {
+        let revision: u32 = self.revision + 1_u32;
+        self.floors.mark(floors, revision);
+        self.elevators.mark(elevators, revision);
+        self.upgrades.mark(upgrades, revision);
+        self.scalars.mark(scalars, revision);
+    }
+
+    /// Record the JSON serialized outcome of a purchase in the next
+    /// revision, forgetting the oldest outcome once more than
+    /// `MAX_PURCHASE_HISTORY` are remembered
+    pub fn record_purchase(&mut self, purchase: JsonValue) {
+        self.purchases.push_back((self.revision + 1_u32, purchase));
+        self.num_purchases += 1_usize;
+        if self.purchases.len() > MAX_PURCHASE_HISTORY {
+            self.purchases.pop_front();
+        }
+    }
+
+    /// Get the outcome of each purchase recorded since the game state
+    /// was last read, marking them as read by the game state.  Diffs
+    /// still report them.
+    pub fn take_unreported_purchases(&mut self) -> JsonValue {
+        let num_unreported: usize = usize::min(self.num_purchases - self.num_purchases_reported, self.purchases.len());
+        let first_unreported: usize = self.purchases.len() - num_unreported;
+        self.num_purchases_reported = self.num_purchases;
+        JsonValue::Array(self.purchases.iter()
+            .skip(first_unreported)
+            .map(|(_, purchase)| purchase.clone())
+            .collect())
+    }
+
+    /// Number a new revision to diff the game state up to from the
+    /// given revision, returning the revision to diff from.  A revision
+    /// this tracker has not reached yet, such as one from a previous
+    /// game, is treated as revision 0, from which the whole state is
+    /// diffed.
+    pub fn next_revision(&mut self, since: u32) -> u32 {
+        let since: u32 = if since > self.revision { 0_u32 } else { since };
+        self.revision += 1_u32;
+        since
+    }
+
+    /// Get the latest revision numbered
+    pub fn get_revision(&self) -> u32 {
+        self.revision
+    }
+
+    /// Get the indices of the floors which changed after the given
+    /// revision
+    pub fn changed_floors(&self, since: u32) -> Vec<usize> {
+        self.floors.changed_since(since)
+    }
+
+    /// Get the indices of the elevators which changed after the given
+    /// revision
+    pub fn changed_elevators(&self, since: u32) -> Vec<usize> {
+        self.elevators.changed_since(since)
+    }
+
+    /// Get the registry indices of the upgrades which changed after the
+    /// given revision
+    pub fn changed_upgrades(&self, since: u32) -> Vec<usize> {
+        self.upgrades.changed_since(since)
+    }
+
+    /// Get the indices of the scalar fields which changed after the
+    /// given revision
+    pub fn changed_scalars(&self, since: u32) -> Vec<usize> {
+        self.scalars.changed_since(since)
+    }
+
+    /// Get the outcome of each purchase recorded after the given
+    /// revision which is still remembered
+    pub fn get_purchases_since(&self, since: u32) -> JsonValue {
+        JsonValue::Array(self.purchases.iter()
+            .filter(|(recorded_in, _)| *recorded_in > since)
+            .map(|(_, purchase)| purchase.clone())
+            .collect())
+    }
+}
\ No newline at end of file
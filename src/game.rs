@@ -1,123 +1,192 @@
 //Import standard/external libraries
+use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
-use json::object;
-use elevate_lib::controller::ElevatorController;
+use rand_chacha::ChaCha12Rng;
+use json::{object, JsonValue};
 use elevate_lib::elevators::Elevators;
 use elevate_lib::floors::Floors;
 use elevate_lib::people::People;
 use elevate_lib::building::Building;
 
 //Input source libraries
-use crate::input::ElevatorGameInput;
-use crate::upgrade::{ElevatorGameUpgrade, ElevatorGameUpgrades};
+use crate::billing::ElectricityBill;
+use crate::config::BaseStats;
+use crate::context::GameContext;
+use crate::controller::{self, ControllerKind, GameController};
+use crate::diff::{self, StateDiffTracker};
+use crate::input::{ElevatorGameInput, ElevatorGameInputError, GameAction, PurchaseQuantity, StopCondition};
+use crate::offline::{OfflineProgress, OfflineSummary};
+use crate::purchase::{PurchaseError, PurchaseRecord, PurchaseTarget, Receipt, UpgradeScope};
+use crate::replay::ElevatorGameInputLog;
+use crate::upgrade::{self, ElevatorGameUpgrade, ElevatorGameUpgrades, Prerequisite, TargetedUpgrade, Upgrade, MAX_BULK_QUANTITY};
+use crate::save::{self, ElevatorGameSaveError};
+
+/// Salt mixed into the game seed to seed the controller's RNG, so that
+/// the controller and the game draw from independent streams
+pub const CONTROLLER_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15_u64;
+
+/// The current version of the game state schema.  Bump this whenever
+/// fields are changed in or removed from `get_game_state`.
+pub const STATE_VERSION: u64 = 1_u64;
+
+/// The fields of each upgrade's state which never change during a
+/// game, so they are left out of state diffs and only exported with
+/// the static metadata
+pub const STATIC_UPGRADE_FIELDS: [&str; 4] = ["name", "description", "scope", "max_buys"];
+
+/// The fields of the game state outside of its floors, elevators,
+/// upgrades, purchases and tech tree, in the order they are tracked in
+/// for state diffs
+const SCALAR_FIELDS: [&str; 10] = [
+    "version",
+    "avg_energy_spent",
+    "avg_wait_time",
+    "building_tips",
+    "collected_tips",
+    "people_served",
+    "total_energy_spent",
+    "electricity",
+    "seed",
+    "controller"
+];
+
+/// The number of floors an elevator travels per time step before any
+/// speed upgrades
+pub const BASE_ELEVATOR_SPEED: usize = 1_usize;
+
+/// The most time steps a single call into the game runs, whether a
+/// batch of steps, a replay or a catch-up on time spent away, so that a
+/// host asking for a huge number of steps does not freeze the page
+pub const MAX_STEPS_PER_BATCH: usize = 10_000_usize;
 
 /// # `ElevatorGame` struct
 ///
 /// The `ElevatorGame` is the main Universal Elevators game object.
 pub struct ElevatorGame {
-    controller: Box<dyn ElevatorController + Send>,
+    controller: Box<dyn GameController>,
+    controller_kind: ControllerKind,
+    p_in: f64,
     upgrades: ElevatorGameUpgrades,
     tips: f64,
-    rng: StdRng,
-    time_steps: i32
+    electricity: ElectricityBill,
+    people_served: usize,
+    total_energy_spent: f64,
+    seed: u64,
+    rng: ChaCha12Rng,
+    time_steps: i32,
+    input_log: ElevatorGameInputLog,
+    dwell_remaining: Vec<usize>,
+    dispatch_floors: Vec<Option<usize>>,
+    base_stats: BaseStats,
+    state_diff: StateDiffTracker
 }
 
 //Implement the ElevatorGame interface
 impl ElevatorGame {
-    /// Initialize a new ElevatorGame given an `ElevatorController`
-    /// implementation and a `StdRng` (from the rand library).
+    /// Initialize a new ElevatorGame given the kind of controller to
+    /// run its building with, the rate people arrive at the building,
+    /// which elevate-lib keeps private, the electricity tariff its
+    /// elevators are billed by, the base stats of its floors and
+    /// elevators before any upgrades, and a seed.  Both the game RNG
+    /// and the controller's RNG are seeded from the seed.
     ///
     /// ## Example
     ///
-    /// ```
-    /// let controller_rng = rand::StdRng::from_seed(rand::thread_rng().gen());
-    /// let my_rng = rand::StdRng::from_seed(rand::thread_rng().gen());
+    /// ```ignore
     /// let my_building: Building = Building::from(
     ///     4_usize,
     ///     2_usize,
     ///     0.5_f64,
+    ///     100_usize,
+    ///     10_usize,
     ///     5.0_f64,
     ///     2.5_f64,
     ///     0.5_f64
     /// );
-    /// let my_controller: RandomController = RandomController::from(
-    ///     my_building,
-    ///     controller_rng
-    /// );
     /// let my_game: ElevatorGame = ElevatorGame::from(
-    ///     Box::new(my_controller),
-    ///     my_rng
+    ///     ControllerKind::Random,
+    ///     my_building,
+    ///     0.5_f64,
+    ///     ElevatorGameUpgrades::new(),
+    ///     ElectricityBill::new(),
+    ///     ElevatorGameConfig::new().get_base_stats(),
+    ///     20240317_u64
     /// );
     /// ```
-    pub fn from(controller: Box<dyn ElevatorController + Send>, upgrades: ElevatorGameUpgrades, rng: StdRng) -> ElevatorGame {
+    pub fn from(controller_kind: ControllerKind, building: Building, p_in: f64, upgrades: ElevatorGameUpgrades,
+                electricity: ElectricityBill, base_stats: BaseStats, seed: u64) -> ElevatorGame {
         //Initialize the game
-        ElevatorGame {
-            controller: controller,
+        let mut game: ElevatorGame = ElevatorGame {
+            controller: controller_kind.build(building, StdRng::seed_from_u64(seed ^ CONTROLLER_SEED_SALT)),
+            controller_kind: controller_kind,
+            p_in: p_in,
             upgrades: upgrades,
             tips: 0.0_f64,
-            rng: rng,
-            time_steps: 0_i32
-        }
+            electricity: electricity,
+            people_served: 0_usize,
+            total_energy_spent: 0.0_f64,
+            seed: seed,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            time_steps: 0_i32,
+            input_log: ElevatorGameInputLog::new(seed, 0_i32),
+            dwell_remaining: Vec::new(),
+            dispatch_floors: Vec::new(),
+            base_stats: base_stats,
+            state_diff: StateDiffTracker::new()
+        };
+
+        //Unlock the upgrades whose prerequisites the starting building
+        //meets
+        game.upgrades.update_unlocks(game.controller.get_building());
+        game
     }
 
-    /// Update the game state, for now this just increments the
-    /// counter.
+    /// Get the seed the game was initialized with
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Update the game state by one time step, running each of the
+    /// player's actions in order before the building is updated.
     pub fn update_game_state(&mut self, input: ElevatorGameInput) {
+        //Record the input in the input log
+        self.input_log.record_input(self.time_steps, &input);
+
+        //Run each action the player took, in order
+        for action in input.actions.iter() {
+            match action {
+                GameAction::CollectTips => {
+                    self.record_purchase("collect_tips", PurchaseTarget::Building, 1_usize);
+                },
+                GameAction::Buy { upgrade_id, target, quantity } => {
+                    let quantity: usize = self.resolve_quantity(upgrade_id, *target, *quantity);
+                    self.record_purchase(upgrade_id, *target, quantity);
+                },
+                GameAction::SelectController(kind) => self.select_controller(*kind),
+                GameAction::Dispatch { elevator, floor } => self.dispatch(*elevator, *floor),
+                GameAction::Hold { elevator } => self.hold(*elevator)
+            }
+        }
+
         //Make updates to the building prior to updating its elevators
         {
             //Mutably borrow the controller's building
             let building: &mut Building = self.controller.get_building_mut();
 
-            //If the player collected tips, then collect the tips from the
-            //building
-            if input.collect_tips {
-                self.tips += building.collect_tips();
-            }
-
-            //If the player added a floor or elevator, then add the floor
-            //and/or elevator to the building
-            if input.append_floor && self.upgrades.append_floor.is_enough(self.tips) {
-                let cost: f64 = self.upgrades.append_floor.buy();
-                self.tips -= cost;
-                let capacity: usize = building.floors[0].capacity;
-                building.append_floor(capacity);
-            }
-            if input.append_elevator && self.upgrades.append_elevator.is_enough(self.tips) {
-                let cost: f64 = self.upgrades.append_elevator.buy();
-                self.tips -= cost;
-                let capacity: usize = building.elevators[0].capacity;
-                let energy_up: f64 = building.elevators[0].energy_up;
-                let energy_down: f64 = building.elevators[0].energy_down;
-                let energy_coef: f64 = building.elevators[0].energy_coef;
-                building.append_elevator(capacity, energy_up, energy_down, energy_coef);
-            }
-
-            //If the player added capacity to their floors or elevators,
-            //then update their capacities
-            if input.add_floor_capacity && self.upgrades.add_floor_capacity.is_enough(self.tips) {
-                let cost: f64 = self.upgrades.add_floor_capacity.buy();
-                self.tips -= cost;
-                let current_capacity: usize = building.floors[0].capacity;
-                building.floors.update_capacities(current_capacity + 100);
-            }
-            if input.add_elevator_capacity && self.upgrades.add_elevator_capacity.is_enough(self.tips) {
-                let cost: f64 = self.upgrades.add_elevator_capacity.buy();
-                self.tips -= cost;
-                let current_capacity: usize = building.elevators[0].capacity;
-                building.elevators.update_capacities(current_capacity + 10);
-            }
-
             //Generate people arriving and leaving
             building.gen_people_arriving(&mut self.rng);
             building.gen_people_leaving(&mut self.rng);
 
-            //Move people on and off the elevators and out of the building
+            //Move people on and off the elevators and out of the building,
+            //counting the people who left as served
+            let num_people: usize = count_people(building);
             building.flush_and_update_tips(&mut self.rng);
+            self.people_served += num_people - count_people(building);
             building.exchange_people_on_elevator();
         }
 
         //Update the building's elevators
-        self.controller.update_elevators();
+        let extra_energy_spent: f64 = self.update_elevators();
 
         //Make updates to the building after updading its elevators
         {
@@ -125,69 +194,1011 @@ impl ElevatorGame {
             let building: &mut Building = self.controller.get_building_mut();
 
             //Increment the wait times, update average energy, update dest probabilities
-            let energy_spent: f64 = building.elevators.get_energy_spent();
+            let energy_spent: f64 = building.elevators.get_energy_spent() + extra_energy_spent;
+            self.total_energy_spent += energy_spent;
             building.increment_wait_times();
             building.update_average_energy(self.time_steps, energy_spent);
             building.update_dest_probabilities();
+
+            //Pay the electricity bill out of the collected tips whenever
+            //it comes due, which may leave the player in debt
+            if let Some(amount_due) = self.electricity.record(self.time_steps, energy_spent) {
+                self.tips -= amount_due;
+            }
         }
 
+        //Unlock the upgrades whose prerequisites are now met
+        self.upgrades.update_unlocks(self.controller.get_building());
+
         //Increment the time step counter
         self.time_steps += 1_i32;
     }
 
-    /// Get the game state, for now this just returns a string with
-    /// the counter state.
-    pub fn get_game_state(&mut self) -> String {
-        //Borrow the controller's building
+    /// Step the game forward up to the given number of time steps, and
+    /// at most `MAX_STEPS_PER_BATCH`, giving the input on the first time
+    /// step only.  If a stop condition is given, then stepping stops as
+    /// soon as it is met, which may be before the first time step.
+    /// Returns the number of time steps run.
+    pub fn step_many(&mut self, num_steps: usize, input: ElevatorGameInput, stop: Option<&StopCondition>) -> usize {
+        let num_steps: usize = usize::min(num_steps, MAX_STEPS_PER_BATCH);
+        let mut input: ElevatorGameInput = input;
+        for num_steps_run in 0_usize..num_steps {
+            if stop.is_some_and(|condition| self.is_stop_met(condition)) {
+                return num_steps_run;
+            }
+            let step_input: ElevatorGameInput = std::mem::replace(&mut input, ElevatorGameInput::empty());
+            self.update_game_state(step_input);
+        }
+        num_steps
+    }
+
+    /// Get the number of floors in the building
+    pub fn get_num_floors(&mut self) -> usize {
+        self.controller.get_building().floors.len()
+    }
+
+    /// Check whether a stop condition is met by the game
+    pub fn is_stop_met(&mut self, condition: &StopCondition) -> bool {
         let building: &Building = self.controller.get_building();
+        match condition {
+            StopCondition::TipsAtLeast(amount) => self.tips + building.tot_tips >= *amount,
+            StopCondition::FloorFull(Some(floor_index)) => building.floors.get(*floor_index)
+                .is_some_and(|floor| floor.get_num_people() >= floor.capacity),
+            StopCondition::FloorFull(None) => building.floors.iter()
+                .any(|floor| floor.get_num_people() >= floor.capacity)
+        }
+    }
+
+    /// Get the game state as a JSON serialized string.  The schema is
+    /// versioned by `STATE_VERSION`.
+    pub fn get_game_state(&mut self) -> String {
+        json::stringify(self.get_game_state_json())
+    }
 
-        //Initialize a game state string
-        let mut game_state = object!{
+    /// Get the game state as a JSON object, forgetting the purchases
+    /// reported in it.  Diffs of the game state still report them.
+    pub fn get_game_state_json(&mut self) -> JsonValue {
+        //Initialize a game state object with the scalar fields
+        let mut game_state: JsonValue = object!{
             floors: [],
             elevators: [],
-            upgrades: {
-                append_floor: {
-                    name: self.upgrades.append_floor.get_name(),
-                    description: self.upgrades.append_floor.get_description(),
-                    cost: self.upgrades.append_floor.get_cost()
-                },
-                append_elevator: {
-                    name: self.upgrades.append_elevator.get_name(),
-                    description: self.upgrades.append_elevator.get_description(),
-                    cost: self.upgrades.append_elevator.get_cost()
-                }
-            },
-            avg_energy_spent: building.avg_energy,
-            avg_wait_time: building.avg_wait_time,
-            building_tips: building.tot_tips,
-            collected_tips: self.tips
+            upgrades: {},
+            purchases: self.state_diff.take_unreported_purchases(),
+            tech_tree: {
+                nodes: [],
+                edges: self.get_tech_tree_edges()
+            }
         };
+        for field in SCALAR_FIELDS.iter() {
+            game_state[*field] = self.get_scalar_state(field);
+        }
 
-        //Append the floor state for each floor
-        for floor in building.floors.iter() {
-            //Add the floor state to the game state object
-            let _ = game_state["floors"].push(
+        //Add the upgrade state for each upgrade in the registry, along
+        //with a node in the tech tree for it
+        let building: &Building = self.controller.get_building();
+        for registered_upgrade in self.upgrades.iter() {
+            let id: &str = registered_upgrade.get_id();
+            game_state["upgrades"][id] = get_upgrade_entry(registered_upgrade, building, &self.upgrades, self.tips);
+            let _ = game_state["tech_tree"]["nodes"].push(
                 object!{
-                    num_people: floor.get_num_people(),
-                    capacity: floor.capacity,
-                    are_people_waiting: floor.are_people_waiting()
+                    id: id,
+                    locked: !registered_upgrade.is_unlocked(),
+                    bought: registered_upgrade.get_num_buys() > 0_usize
                 }
             );
         }
 
-        //Append the elevator state for each floor 
-        for elevator in building.elevators.iter() {
-            //Add the elevator state to the game state object
-            let _ = game_state["elevators"].push(
-                object!{
-                    num_people: elevator.get_num_people(),
-                    capacity: elevator.capacity,
-                    floor_on: elevator.floor_on
+        //Append the state of each floor and elevator
+        for floor_index in 0_usize..self.get_num_floors() {
+            let _ = game_state["floors"].push(self.get_floor_state(floor_index));
+        }
+        let num_elevators: usize = self.controller.get_building().elevators.len();
+        for elevator_index in 0_usize..num_elevators {
+            let _ = game_state["elevators"].push(self.get_elevator_state(elevator_index));
+        }
+
+        //Return the game state object
+        game_state
+    }
+
+    /// Get the state of the scalar field of the game state with the
+    /// given name, one of `SCALAR_FIELDS`
+    fn get_scalar_state(&mut self, field: &str) -> JsonValue {
+        let building: &Building = self.controller.get_building();
+        match field {
+            "version" => STATE_VERSION.into(),
+            "avg_energy_spent" => building.avg_energy.into(),
+            "avg_wait_time" => building.avg_wait_time.into(),
+            "building_tips" => building.tot_tips.into(),
+            "collected_tips" => self.tips.into(),
+            "people_served" => self.people_served.into(),
+            "total_energy_spent" => self.total_energy_spent.into(),
+            "electricity" => self.electricity.to_json(self.time_steps),
+            "seed" => self.seed.to_string().into(),
+            "controller" => self.controller_kind.as_str().into(),
+            _ => JsonValue::Null
+        }
+    }
+
+    /// Get the state of the floor at the given index, including the
+    /// state of each upgrade bought for single floors.  Floors have no
+    /// tips of their own to report: elevate-lib only draws tips from
+    /// people as they leave the building through the lobby, straight
+    /// into the building-wide `building_tips`.
+    fn get_floor_state(&mut self, floor_index: usize) -> JsonValue {
+        //Count the people waiting to go up and down from the floor
+        let floor = &self.controller.get_building().floors[floor_index];
+        let dest_floors: Vec<usize> = floor.get_dest_floors();
+        let num_going_up: usize = dest_floors.iter()
+            .filter(|floor_to| **floor_to > floor_index)
+            .count();
+        let num_going_down: usize = dest_floors.iter()
+            .filter(|floor_to| **floor_to < floor_index)
+            .count();
+
+        //Initialize the floor state object
+        let mut floor_state: JsonValue = object!{
+            num_people: floor.get_num_people(),
+            capacity: floor.capacity,
+            are_people_waiting: floor.are_people_waiting(),
+            num_people_waiting: num_going_up + num_going_down,
+            num_going_up: num_going_up,
+            num_going_down: num_going_down,
+            upgrades: {}
+        };
+
+        //Add the state of each upgrade bought for single floors
+        for registered_upgrade in self.upgrades.iter_mut() {
+            if registered_upgrade.get_scope() == UpgradeScope::Floor {
+                let id: String = registered_upgrade.get_id().to_string();
+                floor_state["upgrades"][id.as_str()] = upgrade::get_target_state(&registered_upgrade.for_target(floor_index), self.tips);
+            }
+        }
+        floor_state
+    }
+
+    /// Get the state of the elevator at the given index, including the
+    /// state of each upgrade bought for single elevators
+    fn get_elevator_state(&mut self, elevator_index: usize) -> JsonValue {
+        //Gather the floors the elevator's riders are headed to
+        let elevator = &mut self.controller.get_building_mut().elevators[elevator_index];
+        let mut stops: Vec<usize> = elevator.people.iter()
+            .map(|person| person.floor_to)
+            .collect();
+        stops.sort();
+        stops.dedup();
+
+        //Initialize the elevator state object
+        let mut elevator_state: JsonValue = object!{
+            num_people: elevator.get_num_people(),
+            capacity: elevator.capacity,
+            floor_on: elevator.floor_on,
+            direction: if elevator.moving_up { "up" } else { "down" },
+            stopped: elevator.stopped,
+            stops: stops,
+            energy_spent: elevator.get_energy_spent(),
+            energy_up: elevator.energy_up,
+            energy_down: elevator.energy_down,
+            energy_coef: elevator.energy_coef,
+            speed: BASE_ELEVATOR_SPEED + self.upgrades.get_speed_bonus(elevator_index),
+            dwell_time: self.base_stats.dwell_time.saturating_sub(self.upgrades.get_dwell_reduction(elevator_index)),
+            dwell_remaining: self.dwell_remaining.get(elevator_index).copied().unwrap_or(0_usize),
+            dispatch_floor: self.dispatch_floors.get(elevator_index).copied().flatten(),
+            upgrades: {}
+        };
+
+        //Add the state of each upgrade bought for single elevators
+        for registered_upgrade in self.upgrades.iter_mut() {
+            if registered_upgrade.get_scope() == UpgradeScope::Elevator {
+                let id: String = registered_upgrade.get_id().to_string();
+                elevator_state["upgrades"][id.as_str()] = upgrade::get_target_state(&registered_upgrade.for_target(elevator_index), self.tips);
+            }
+        }
+        elevator_state
+    }
+
+    /// Mark the parts of the game state which changed since the last
+    /// diff as changed in the next revision, see `StateDiffTracker::mark`.
+    /// Each floor, elevator, upgrade and scalar field is fingerprinted
+    /// from what its state is built from, without building its state.
+    /// The tips are only fingerprinted through what they can afford, so
+    /// that tips trickling in do not mark every floor and elevator.
+    fn mark_changes(&mut self) {
+        //Fingerprint each floor by its people, capacity and upgrades
+        let tips: f64 = self.tips;
+        let building: &mut Building = self.controller.get_building_mut();
+        let floors: Vec<u64> = building.floors.iter()
+            .enumerate()
+            .map(|(floor_index, floor)| diff::fingerprint(&(
+                floor.get_dest_floors(),
+                floor.get_num_people(),
+                floor.capacity,
+                get_target_fingerprint(&mut self.upgrades, UpgradeScope::Floor, floor_index, tips)
+            )))
+            .collect();
+
+        //Fingerprint each elevator by its movement, riders, energy use,
+        //speed, dwell time, orders and upgrades
+        let mut elevators: Vec<u64> = Vec::new();
+        for (elevator_index, elevator) in building.elevators.iter_mut().enumerate() {
+            let riders: Vec<usize> = elevator.people.iter()
+                .map(|person| person.floor_to)
+                .collect();
+            let energy: [u64; 4] = [
+                elevator.get_energy_spent().to_bits(),
+                elevator.energy_up.to_bits(),
+                elevator.energy_down.to_bits(),
+                elevator.energy_coef.to_bits()
+            ];
+            elevators.push(diff::fingerprint(&(
+                (elevator.floor_on, elevator.moving_up, elevator.stopped),
+                (riders, elevator.capacity, energy),
+                (self.upgrades.get_speed_bonus(elevator_index), self.upgrades.get_dwell_reduction(elevator_index)),
+                (self.dwell_remaining.get(elevator_index).copied(), self.dispatch_floors.get(elevator_index).copied()),
+                get_target_fingerprint(&mut self.upgrades, UpgradeScope::Elevator, elevator_index, tips)
+            )));
+        }
+
+        //Fingerprint each upgrade by its buys, whether it is unlocked, how
+        //many more buys the tips afford and whether each of its
+        //prerequisites is met
+        let upgrades: Vec<u64> = self.upgrades.iter()
+            .map(|registered_upgrade| diff::fingerprint(&(
+                registered_upgrade.get_num_buys(),
+                registered_upgrade.is_unlocked(),
+                match registered_upgrade.get_scope() {
+                    UpgradeScope::Building => upgrade::get_max_affordable(registered_upgrade, tips),
+                    _ => 0_usize
+                },
+                registered_upgrade.get_prerequisites().iter()
+                    .map(|prerequisite| prerequisite.is_met(building, &self.upgrades))
+                    .collect::<Vec<bool>>()
+            )))
+            .collect();
+
+        //Fingerprint each scalar field by its value.  The electricity
+        //bill is small, so it is fingerprinted by its serialization.
+        let scalars: Vec<u64> = SCALAR_FIELDS.iter()
+            .map(|field| match *field {
+                "electricity" => diff::fingerprint(&self.electricity.to_json(self.time_steps).dump()),
+                "avg_energy_spent" => diff::fingerprint(&building.avg_energy.to_bits()),
+                "avg_wait_time" => diff::fingerprint(&building.avg_wait_time.to_bits()),
+                "building_tips" => diff::fingerprint(&building.tot_tips.to_bits()),
+                "collected_tips" => diff::fingerprint(&tips.to_bits()),
+                "people_served" => diff::fingerprint(&self.people_served),
+                "total_energy_spent" => diff::fingerprint(&self.total_energy_spent.to_bits()),
+                "controller" => diff::fingerprint(&self.controller_kind.as_str()),
+                "seed" => diff::fingerprint(&self.seed),
+                _ => diff::fingerprint(&STATE_VERSION)
+            })
+            .collect();
+
+        //Mark the changes
+        self.state_diff.mark(floors, elevators, upgrades, scalars);
+    }
+
+    /// Get the changes to the game state since the given revision as a
+    /// JSON serialized string.  The diff holds the `floors` and
+    /// `elevators` which changed keyed by index, the `upgrades` which
+    /// changed keyed by id, and the other fields which changed under
+    /// `scalars`, along with the `purchases` made since the given
+    /// revision and the number of floors and elevators, so that removed
+    /// entries can be dropped.  A revision the game has not reached yet,
+    /// such as one from a previous game, is treated as revision 0, so
+    /// the whole state is sent and the diff is marked as `full`.  The
+    /// parts of the state which never change during a game, the static
+    /// fields of each upgrade and the tech tree, are left out, since
+    /// they are exported once by `get_static_metadata`.
+    pub fn get_state_diff(&mut self, since_version: u32) -> String {
+        //Mark what changed since the last diff, then number the revision
+        //being diffed up to
+        self.mark_changes();
+        let since: u32 = self.state_diff.next_revision(since_version);
+        let num_floors: usize = self.get_num_floors();
+        let num_elevators: usize = self.controller.get_building().elevators.len();
+        let mut state_diff: JsonValue = object!{
+            revision: self.state_diff.get_revision(),
+            since: since,
+            full: since == 0_u32,
+            num_floors: num_floors,
+            num_elevators: num_elevators,
+            floors: {},
+            elevators: {},
+            upgrades: {},
+            scalars: {},
+            purchases: self.state_diff.get_purchases_since(since)
+        };
+
+        //Add the floors and elevators which changed
+        for floor_index in self.state_diff.changed_floors(since) {
+            state_diff["floors"][floor_index.to_string().as_str()] = self.get_floor_state(floor_index);
+        }
+        for elevator_index in self.state_diff.changed_elevators(since) {
+            state_diff["elevators"][elevator_index.to_string().as_str()] = self.get_elevator_state(elevator_index);
+        }
+
+        //Add the upgrades which changed, without their static fields
+        let changed_upgrades: Vec<usize> = self.state_diff.changed_upgrades(since);
+        let building: &Building = self.controller.get_building();
+        for (upgrade_index, registered_upgrade) in self.upgrades.iter().enumerate() {
+            if changed_upgrades.contains(&upgrade_index) {
+                let mut upgrade_state: JsonValue = get_upgrade_entry(registered_upgrade, building, &self.upgrades, self.tips);
+                for field in STATIC_UPGRADE_FIELDS.iter() {
+                    upgrade_state.remove(field);
                 }
-            );
+                state_diff["upgrades"][registered_upgrade.get_id()] = upgrade_state;
+            }
+        }
+
+        //Add the scalar fields which changed
+        for field_index in self.state_diff.changed_scalars(since) {
+            let field: &str = SCALAR_FIELDS[field_index];
+            state_diff["scalars"][field] = self.get_scalar_state(field);
+        }
+        json::stringify(state_diff)
+    }
+
+    /// Get the parts of the game state which never change during a game
+    /// as a JSON serialized string: the static fields of each upgrade,
+    /// including the prerequisites it needs, and the edges of the tech
+    /// tree.
+    pub fn get_static_metadata(&self) -> String {
+        //Initialize the metadata object
+        let mut metadata = object!{
+            version: STATE_VERSION,
+            seed: self.seed.to_string(),
+            controllers: ControllerKind::ALL.iter()
+                .map(|kind| kind.as_str())
+                .collect::<Vec<&str>>(),
+            upgrades: {},
+            tech_tree: {
+                nodes: [],
+                edges: self.get_tech_tree_edges()
+            }
+        };
+
+        //Add the static fields of each upgrade in the registry
+        for registered_upgrade in self.upgrades.iter() {
+            let id: &str = registered_upgrade.get_id();
+            let max_buys: Option<usize> = match registered_upgrade.get_max_buys() {
+                usize::MAX => None,
+                max_buys => Some(max_buys)
+            };
+            metadata["upgrades"][id] = object!{
+                name: registered_upgrade.get_name(),
+                description: registered_upgrade.get_description(),
+                scope: registered_upgrade.get_scope().as_str(),
+                max_buys: max_buys,
+                prerequisites: registered_upgrade.get_prerequisites().iter()
+                    .map(|prerequisite| prerequisite.to_json())
+                    .collect::<Vec<JsonValue>>()
+            };
+            let _ = metadata["tech_tree"]["nodes"].push(object!{ id: id });
+        }
+
+        //Serialize and return the metadata object
+        json::stringify(metadata)
+    }
+
+    /// Get the edges of the tech tree, from each upgrade to the upgrades
+    /// which need it
+    fn get_tech_tree_edges(&self) -> JsonValue {
+        let mut edges: JsonValue = JsonValue::new_array();
+        for registered_upgrade in self.upgrades.iter() {
+            for prerequisite in registered_upgrade.get_prerequisites().iter() {
+                if let Prerequisite::Upgrade { id, num_buys } = prerequisite {
+                    let _ = edges.push(
+                        object!{
+                            from: id.as_str(),
+                            to: registered_upgrade.get_id(),
+                            num_buys: *num_buys
+                        }
+                    );
+                }
+            }
+        }
+        edges
+    }
+
+    /// Export the full game state as a JSON serialized save string.
+    ///
+    /// The game RNG is saved as its key and position in the stream, so
+    /// exporting a save does not change how the game plays out, and the
+    /// controller saves whatever it keeps track of besides the building.
+    /// A game loaded from the save continues as this game does, except
+    /// for what elevate-lib keeps private: the random controller's RNG
+    /// and chosen floors, see `GameController`, and the number of people
+    /// the average wait time is taken over, see `save::building_from_json`.
+    pub fn export_save(&mut self) -> String {
+        //Initialize the save object
+        let mut save_object = object!{
+            version: save::SAVE_VERSION,
+            seed: self.seed.to_string(),
+            tips: self.tips,
+            electricity: self.electricity.save_to_json(),
+            people_served: self.people_served,
+            total_energy_spent: self.total_energy_spent,
+            time_steps: self.time_steps,
+            rng: save::rng_to_json(&self.rng),
+            controller: self.controller_kind.as_str(),
+            controller_state: self.controller.save_state(),
+            upgrades: {},
+            unlocked: [],
+            target_buys: {},
+            dwell_remaining: self.dwell_remaining.clone(),
+            dispatch_floors: self.dispatch_floors.clone(),
+            building: save::building_to_json(self.controller.get_building())
+        };
+        for registered_upgrade in self.upgrades.iter() {
+            save_object["upgrades"][registered_upgrade.get_id()] = registered_upgrade.get_num_buys().into();
+            if registered_upgrade.is_unlocked() {
+                let _ = save_object["unlocked"].push(registered_upgrade.get_id());
+            }
+            if !registered_upgrade.get_all_target_buys().is_empty() {
+                save_object["target_buys"][registered_upgrade.get_id()] = registered_upgrade.get_all_target_buys().to_vec().into();
+            }
+        }
+
+        //Serialize and return the save object
+        json::stringify(save_object)
+    }
+
+    /// Load the full game state from a JSON serialized save string
+    /// produced by `export_save`.  The game is left untouched if the
+    /// save fails to load.
+    pub fn import_save(&mut self, save_string: String) -> Result<(), ElevatorGameSaveError> {
+        //Parse the save string and migrate it to the current version
+        let save_object: JsonValue = save::migrate(json::parse(&save_string)?)?;
+
+        //Read the game state
+        let seed: u64 = save::get_u64_str(&save_object, "seed")?;
+        let tips: f64 = save::get_f64(&save_object, "tips")?;
+        let electricity: ElectricityBill = self.electricity.restore_from_json(save::get(&save_object, "electricity")?)?;
+        let people_served: usize = save::get_usize(&save_object, "people_served")?;
+        let total_energy_spent: f64 = save::get_f64(&save_object, "total_energy_spent")?;
+        let time_steps: i32 = save::get_i32(&save_object, "time_steps")?;
+        let rng: ChaCha12Rng = save::rng_from_json(save::get(&save_object, "rng")?)?;
+        let upgrades: &JsonValue = save::get(&save_object, "upgrades")?;
+        let unlocked: &JsonValue = save::get(&save_object, "unlocked")?;
+        let target_buys: &JsonValue = save::get(&save_object, "target_buys")?;
+        let mut upgrade_buys: Vec<(usize, bool, Vec<usize>)> = Vec::new();
+        for registered_upgrade in self.upgrades.iter() {
+            upgrade_buys.push((
+                save::get_usize_or(upgrades, registered_upgrade.get_id(), 0_usize)?,
+                unlocked.contains(registered_upgrade.get_id()),
+                save::get_usize_array_or_empty(target_buys, registered_upgrade.get_id())?
+            ));
+        }
+        let dwell_remaining: Vec<usize> = save::get_usize_array_or_empty(&save_object, "dwell_remaining")?;
+        let dispatch_floors: Vec<Option<usize>> = save::get_optional_usize_array(&save_object, "dispatch_floors")?;
+        let controller: &str = save::get(&save_object, "controller")?.as_str()
+            .ok_or(ElevatorGameSaveError::WrongType("controller".to_string()))?;
+        let controller_kind: ControllerKind = ControllerKind::from_str(controller)
+            .ok_or(ElevatorGameSaveError::WrongType("controller".to_string()))?;
+        let building: Building = save::building_from_json(save::get(&save_object, "building")?, self.p_in)?;
+
+        //Restore the controller, seeding a random controller's RNG the
+        //way a new game with the saved seed does
+        let mut controller: Box<dyn GameController> = controller_kind.build(building, StdRng::seed_from_u64(seed ^ CONTROLLER_SEED_SALT));
+        controller.restore_state(save::get(&save_object, "controller_state")?)?;
+
+        //Now that the whole save has been read, overwrite the game state
+        self.seed = seed;
+        self.tips = tips;
+        self.electricity = electricity;
+        self.people_served = people_served;
+        self.total_energy_spent = total_energy_spent;
+        self.time_steps = time_steps;
+        self.input_log = ElevatorGameInputLog::new(seed, time_steps);
+        for (registered_upgrade, (num_buys, is_unlocked, target_buys)) in self.upgrades.iter_mut().zip(upgrade_buys) {
+            registered_upgrade.set_num_buys(num_buys);
+            registered_upgrade.set_unlocked(is_unlocked);
+            registered_upgrade.set_all_target_buys(target_buys);
         }
+        self.dwell_remaining = dwell_remaining;
+        self.dispatch_floors = dispatch_floors;
+        self.controller = controller;
+        self.rng = rng;
+        self.controller_kind = controller_kind;
+        self.upgrades.update_unlocks(self.controller.get_building());
+        Ok(())
+    }
+
+    /// Buy the upgrade with the given id for the given target the given
+    /// number of times, then apply its effect once per buy.  The
+    /// purchase is all or nothing: if any of the buys cannot be made,
+    /// then no tips are spent and the game is left untouched.  At most
+    /// `MAX_BULK_QUANTITY` buys can be made at once.
+    pub fn purchase(&mut self, upgrade_id: &str, target: PurchaseTarget, quantity: usize) -> Result<Receipt, PurchaseError> {
+        //Make sure the target exists
+        let building: &Building = self.controller.get_building();
+        let is_valid_target: bool = match target {
+            PurchaseTarget::Building => true,
+            PurchaseTarget::Elevator(index) => index < building.elevators.len(),
+            PurchaseTarget::Floor(index) => index < building.floors.len()
+        };
+
+        //Make sure something is being bought, the upgrade exists and it
+        //can be bought for the target
+        if quantity == 0_usize || quantity > MAX_BULK_QUANTITY {
+            return Err(PurchaseError::InvalidQuantity { quantity: quantity, max: MAX_BULK_QUANTITY });
+        }
+        let tips: f64 = self.tips;
+        let new_stats: BaseStats = self.upgrades.get_new_stats(&self.base_stats);
+        let registered_upgrade: &mut Upgrade = match self.upgrades.get_mut(upgrade_id) {
+            Some(registered_upgrade) => registered_upgrade,
+            None => return Err(PurchaseError::UnknownUpgrade { id: upgrade_id.to_string() })
+        };
+        if !is_valid_target || registered_upgrade.get_scope() != target.get_scope() {
+            return Err(PurchaseError::InvalidTarget);
+        }
+
+        //Price upgrades bought for a single elevator or floor by that
+        //elevator or floor
+        let mut targeted_upgrade: TargetedUpgrade;
+        let upgrade: &mut dyn ElevatorGameUpgrade = match target {
+            PurchaseTarget::Building => registered_upgrade,
+            PurchaseTarget::Elevator(index) | PurchaseTarget::Floor(index) => {
+                targeted_upgrade = registered_upgrade.for_target(index);
+                &mut targeted_upgrade
+            }
+        };
+
+        //Make sure the upgrade has been unlocked
+        if !upgrade.is_unlocked() {
+            return Err(PurchaseError::Locked);
+        }
+
+        //Make sure the upgrade can be bought that many more times
+        if upgrade.get_num_buys().saturating_add(quantity) > upgrade.get_max_buys() {
+            return Err(PurchaseError::MaxedOut);
+        }
+
+        //Make sure the player can afford every buy
+        let needed: f64 = upgrade.total_cost(quantity);
+        if tips < needed {
+            return Err(PurchaseError::InsufficientFunds { needed: needed, have: tips });
+        }
+
+        //Buy the upgrade
+        let mut cost: f64 = 0.0_f64;
+        for _ in 0_usize..quantity {
+            cost += upgrade.buy()?;
+        }
+
+        //Let the upgrade apply itself once per buy
+        let mut context: GameContext = GameContext::new(tips - cost, self.controller_kind, target, new_stats);
+        let building: &mut Building = self.controller.get_building_mut();
+        for _ in 0_usize..quantity {
+            upgrade.apply(building, &mut context);
+        }
+
+        //Read back the parts of the game the upgrade may have changed
+        self.tips = context.tips;
+        if context.controller_kind != self.controller_kind {
+            self.set_controller(context.controller_kind);
+        }
+        Ok(Receipt {
+            upgrade_id: upgrade_id.to_string(),
+            target: target,
+            quantity: quantity,
+            cost: cost
+        })
+    }
+
+    /// Get the number of times to buy an upgrade for a target given the
+    /// quantity the player asked for.  Buying the most the player can
+    /// afford buys at least once, so that a player who can afford none
+    /// is told why.
+    fn resolve_quantity(&mut self, upgrade_id: &str, target: PurchaseTarget, quantity: PurchaseQuantity) -> usize {
+        let tips: f64 = self.tips;
+        match quantity {
+            PurchaseQuantity::Count(count) => count,
+            PurchaseQuantity::Max => match (self.upgrades.get_mut(upgrade_id), target) {
+                (Some(upgrade), PurchaseTarget::Building) => usize::max(upgrade.max_affordable(tips), 1_usize),
+                (Some(upgrade), PurchaseTarget::Elevator(index)) | (Some(upgrade), PurchaseTarget::Floor(index)) =>
+                    usize::max(upgrade.for_target(index).max_affordable(tips), 1_usize),
+                (None, _) => 1_usize
+            }
+        }
+    }
+
+    /// Attempt a purchase on behalf of the player, recording its outcome
+    /// so that it is reported in the game state
+    fn record_purchase(&mut self, upgrade_id: &str, target: PurchaseTarget, quantity: usize) {
+        let result: Result<Receipt, PurchaseError> = self.purchase(upgrade_id, target, quantity);
+        let record: PurchaseRecord = PurchaseRecord {
+            time_step: self.time_steps,
+            upgrade_id: upgrade_id.to_string(),
+            target: target,
+            quantity: quantity,
+            result: result
+        };
+        self.state_diff.record_purchase(record.to_json());
+    }
+
+    /// Catch up on the given number of seconds the player spent away
+    /// from the game, running as many time steps as the time away is
+    /// worth, up to the cap.  A player driving the elevators by hand
+    /// hands them to the best automatic controller they own for the
+    /// catch-up, which is left running afterwards.  Returns a summary
+    /// of what happened while the player was away.
+    pub fn advance_offline(&mut self, elapsed_seconds: f64, offline: &OfflineProgress) -> OfflineSummary {
+        //Remember where the game stood before the catch-up
+        let (num_steps, is_capped) = offline.get_num_steps(elapsed_seconds);
+        let tips_before: f64 = self.controller.get_building().tot_tips;
+        let people_served_before: usize = self.people_served;
+        let energy_spent_before: f64 = self.total_energy_spent;
+        let energy_billed_before: f64 = self.electricity.total_billed;
+
+        //Switch to the best owned automatic controller on the first step,
+        //as an action so that replays switch at the same time step
+        let mut input: ElevatorGameInput = ElevatorGameInput::empty();
+        if self.controller_kind == ControllerKind::Manual && num_steps > 0_usize {
+            if let Some(kind) = self.get_best_automation() {
+                input.actions.push(GameAction::SelectController(kind));
+            }
+        }
+
+        //Run the time steps without any further input
+        for _ in 0_usize..num_steps {
+            let step_input: ElevatorGameInput = std::mem::replace(&mut input, ElevatorGameInput::empty());
+            self.update_game_state(step_input);
+        }
+
+        //Sum up the catch-up
+        OfflineSummary {
+            elapsed_seconds: elapsed_seconds,
+            num_steps: num_steps,
+            is_capped: is_capped,
+            tips_earned: self.controller.get_building().tot_tips - tips_before,
+            people_served: self.people_served - people_served_before,
+            energy_spent: self.total_energy_spent - energy_spent_before,
+            energy_billed: self.electricity.total_billed - energy_billed_before
+        }
+    }
+
+    /// Get the most sophisticated automatic controller the player owns,
+    /// if any.  Controllers without an upgrade are free, so they are
+    /// always owned.
+    fn get_best_automation(&mut self) -> Option<ControllerKind> {
+        ControllerKind::ALL.iter()
+            .rev()
+            .copied()
+            .filter(|kind| *kind != ControllerKind::Manual)
+            .find(|kind| match self.upgrades.get_controller_mut(*kind) {
+                Some(upgrade) => upgrade.get_num_buys() > 0_usize,
+                None => true
+            })
+    }
+
+    /// Switch to the controller of the given kind, buying it first if it
+    /// is not owned yet
+    fn select_controller(&mut self, kind: ControllerKind) {
+        let unowned_id: Option<String> = match self.upgrades.get_controller_mut(kind) {
+            Some(upgrade) if upgrade.get_num_buys() == 0_usize => Some(upgrade.get_id().to_string()),
+            _ => None
+        };
+        match unowned_id {
+            Some(id) => self.record_purchase(&id, PurchaseTarget::Building, 1_usize),
+            None => if kind != self.controller_kind { self.set_controller(kind); }
+        }
+    }
+
+    /// Order an elevator to the given floor, replacing any order it was
+    /// already given.  Orders for elevators or floors which do not exist
+    /// are ignored.
+    fn dispatch(&mut self, elevator_index: usize, floor_index: usize) {
+        let building: &Building = self.controller.get_building();
+        let num_elevators: usize = building.elevators.len();
+        if elevator_index >= num_elevators || floor_index >= building.floors.len() {
+            return;
+        }
+        self.dispatch_floors.resize(num_elevators, None);
+        self.dispatch_floors[elevator_index] = Some(floor_index);
+    }
+
+    /// Hold an elevator's doors open for the time step, stopping it
+    /// where it is and dropping any order it was given.  Holds for
+    /// elevators which do not exist are ignored.
+    fn hold(&mut self, elevator_index: usize) {
+        let num_elevators: usize = self.controller.get_building().elevators.len();
+        if elevator_index >= num_elevators {
+            return;
+        }
+        self.dispatch_floors.resize(num_elevators, None);
+        self.dispatch_floors[elevator_index] = None;
+        self.dwell_remaining.resize(num_elevators, 0_usize);
+        self.dwell_remaining[elevator_index] = usize::max(self.dwell_remaining[elevator_index], 1_usize);
+    }
+
+    /// Update the building's elevators through the controller, then
+    /// apply each elevator's dispatch order, dwell time and speed.  An
+    /// elevator holding its doors open stays where it was, an elevator
+    /// the player dispatched heads to its ordered floor wherever the
+    /// controller sent it, and a faster elevator keeps moving past floors
+    /// where nobody is getting on or off.  Returns the energy spent
+    /// moving the faster elevators past their first floor this time step.
+    fn update_elevators(&mut self) -> f64 {
+        //Work out each elevator's speed and dwell time from its upgrades
+        let num_elevators: usize = self.controller.get_building().elevators.len();
+        let speeds: Vec<usize> = (0_usize..num_elevators)
+            .map(|index| BASE_ELEVATOR_SPEED + self.upgrades.get_speed_bonus(index))
+            .collect();
+        let dwell_times: Vec<usize> = (0_usize..num_elevators)
+            .map(|index| self.base_stats.dwell_time.saturating_sub(self.upgrades.get_dwell_reduction(index)))
+            .collect();
+        self.dwell_remaining.resize(num_elevators, 0_usize);
+        self.dispatch_floors.resize(num_elevators, None);
+
+        //Remember where each elevator was before the controller moves it
+        let before: Vec<(usize, bool, bool)> = self.controller.get_building().elevators.iter()
+            .map(|elevator| (elevator.floor_on, elevator.moving_up, elevator.stopped))
+            .collect();
+        self.controller.update_elevators();
+
+        //Find the floors where people are waiting to go somewhere
+        let building: &mut Building = self.controller.get_building_mut();
+        let num_floors: usize = building.floors.len();
+        let call_floors: Vec<bool> = building.floors.iter()
+            .map(|floor| floor.are_people_waiting())
+            .collect();
+
+        //Adjust each elevator's movement
+        let mut extra_energy_spent: f64 = 0.0_f64;
+        for (index, elevator) in building.elevators.iter_mut().enumerate() {
+            //Hold an elevator which is holding its doors open in place
+            let (floor_on, moving_up, was_stopped) = before[index];
+            if self.dwell_remaining[index] > 0_usize {
+                elevator.floor_on = floor_on;
+                elevator.moving_up = moving_up;
+                elevator.stopped = true;
+                self.dwell_remaining[index] -= 1_usize;
+                continue;
+            }
+
+            //Send a dispatched elevator to its ordered floor in place of
+            //wherever the controller sent it, dropping the order once the
+            //elevator stops there
+            if let Some(dispatch_floor) = self.dispatch_floors[index] {
+                elevator.floor_on = floor_on;
+                elevator.moving_up = moving_up;
+                controller::move_toward(elevator, Some(dispatch_floor));
+                if elevator.stopped {
+                    self.dispatch_floors[index] = None;
+                }
+            }
+
+            //Start holding the doors open on an elevator which just stopped
+            if elevator.stopped {
+                if !was_stopped {
+                    self.dwell_remaining[index] = dwell_times[index];
+                }
+                continue;
+            }
+
+            //Keep a faster elevator moving until it reaches a floor where
+            //someone gets on or off, its ordered floor, or the end of the
+            //shaft
+            for _ in BASE_ELEVATOR_SPEED..speeds[index] {
+                let is_stop: bool = call_floors[elevator.floor_on] ||
+                    self.dispatch_floors[index] == Some(elevator.floor_on) ||
+                    elevator.people.iter().any(|person| person.floor_to == elevator.floor_on);
+                let is_end: bool = if elevator.moving_up {
+                    elevator.floor_on + 1_usize >= num_floors
+                } else {
+                    elevator.floor_on == 0_usize
+                };
+                if is_stop || is_end {
+                    break;
+                }
+                extra_energy_spent += elevator.get_energy_spent();
+                elevator.update_floor();
+            }
+        }
+        extra_energy_spent
+    }
+
+    /// Replace the building's controller with a controller of the given
+    /// kind, seeding its RNG from the game RNG
+    fn set_controller(&mut self, kind: ControllerKind) {
+        let building: Building = self.controller.clone_building();
+        let controller_rng: StdRng = StdRng::seed_from_u64(self.rng.gen());
+        self.controller = kind.build(building, controller_rng);
+        self.controller_kind = kind;
+    }
+
+    /// Export the input log as a JSON serialized string.  The log only
+    /// covers the game since it was initialized or last loaded from a
+    /// save.
+    pub fn export_input_log(&self) -> String {
+        json::stringify(self.input_log.to_json())
+    }
+
+    /// Replay an input log on this game, which must be freshly
+    /// initialized from the same config and seed as the recorded game.
+    /// Steps without a recorded input are replayed with an empty input.
+    /// Logs longer than `MAX_STEPS_PER_BATCH` time steps are rejected.
+    pub fn replay(&mut self, log: &ElevatorGameInputLog) -> Result<(), ElevatorGameInputError> {
+        //Make sure the log was recorded with the game's seed
+        if log.seed != self.seed {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "seed".to_string(),
+                value: log.seed.to_string()
+            });
+        }
+
+        //Make sure both the log and the game start from the beginning
+        if log.start_time_step != 0_i32 || self.time_steps != 0_i32 {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "start_time_step".to_string(),
+                value: log.start_time_step.to_string()
+            });
+        }
+
+        //Make sure the log can be replayed in a single call
+        if log.time_steps as usize > MAX_STEPS_PER_BATCH {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "time_steps".to_string(),
+                value: log.time_steps.to_string()
+            });
+        }
+
+        //Step through the log
+        let mut inputs = log.inputs.iter().peekable();
+        while self.time_steps < log.time_steps {
+            //Replay the input recorded for this step, if any
+            let mut input: ElevatorGameInput = ElevatorGameInput::empty();
+            while let Some((input_time_step, recorded_input)) = inputs.peek() {
+                if *input_time_step > self.time_steps {
+                    break;
+                }
+                if *input_time_step == self.time_steps {
+                    input = recorded_input.clone();
+                }
+                inputs.next();
+            }
+            self.update_game_state(input);
+        }
+        Ok(())
+    }
+}
+
+/// Get the state of an upgrade in the registry, along with whether
+/// each of its prerequisites is met by the given building and upgrades
+fn get_upgrade_entry(registered_upgrade: &Upgrade, building: &Building, upgrades: &ElevatorGameUpgrades, tips: f64) -> JsonValue {
+    let mut upgrade_state: JsonValue = upgrade::get_upgrade_state(registered_upgrade, tips);
+    upgrade_state["prerequisites"] = JsonValue::new_array();
+    for prerequisite in registered_upgrade.get_prerequisites().iter() {
+        let mut prerequisite_state: JsonValue = prerequisite.to_json();
+        prerequisite_state["met"] = prerequisite.is_met(building, upgrades).into();
+        let _ = upgrade_state["prerequisites"].push(prerequisite_state);
+    }
+    upgrade_state
+}
+
+/// Fingerprint the levels of the upgrades of the given scope bought for
+/// the target at the given index, along with whether they are unlocked
+/// and how many more buys of them the given tips afford
+fn get_target_fingerprint(upgrades: &mut ElevatorGameUpgrades, scope: UpgradeScope, index: usize, tips: f64) -> u64 {
+    let levels: Vec<(usize, bool, usize)> = upgrades.iter_mut()
+        .filter(|registered_upgrade| registered_upgrade.get_scope() == scope)
+        .map(|registered_upgrade| {
+            let targeted_upgrade: TargetedUpgrade = registered_upgrade.for_target(index);
+            (
+                targeted_upgrade.get_num_buys(),
+                targeted_upgrade.is_unlocked(),
+                upgrade::get_max_affordable(&targeted_upgrade, tips)
+            )
+        })
+        .collect();
+    diff::fingerprint(&levels)
+}
+
+/// Count the people in a building, on its floors and its elevators
+fn count_people(building: &Building) -> usize {
+    let num_on_floors: usize = building.floors.iter().map(|floor| floor.get_num_people()).sum();
+    let num_on_elevators: usize = building.elevators.iter().map(|elevator| elevator.get_num_people()).sum();
+    num_on_floors + num_on_elevators
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ElevatorGameConfig;
+
+    /// Build a game with tips to spare and the upgrade with the given id
+    /// unlocked
+    fn game_with_unlocked(upgrade_id: &str) -> ElevatorGame {
+        let mut game: ElevatorGame = ElevatorGameConfig::new().build_with_seed(1_u64);
+        game.tips = 1.0e12_f64;
+        game.upgrades.get_mut(upgrade_id).unwrap().set_unlocked(true);
+        game
+    }
+
+    /// Play a game for a few hundred time steps, collecting its tips
+    /// every so often
+    fn play(game: &mut ElevatorGame) {
+        for step in 0_usize..300_usize {
+            let mut input: ElevatorGameInput = ElevatorGameInput::empty();
+            if step % 25_usize == 24_usize {
+                input.actions.push(GameAction::CollectTips);
+            }
+            game.update_game_state(input);
+        }
+    }
+
+    #[test]
+    fn same_seed_and_inputs_play_out_the_same() {
+        let mut game: ElevatorGame = ElevatorGameConfig::new().build_with_seed(5_u64);
+        let mut other_game: ElevatorGame = ElevatorGameConfig::new().build_with_seed(5_u64);
+        play(&mut game);
+        play(&mut other_game);
+        assert_eq!(game.get_game_state(), other_game.get_game_state());
+    }
+
+    #[test]
+    fn replaying_the_input_log_reproduces_the_game() {
+        let mut game: ElevatorGame = ElevatorGameConfig::new().build_with_seed(5_u64);
+        play(&mut game);
+        let log: ElevatorGameInputLog = ElevatorGameInputLog::from_json(game.export_input_log()).unwrap();
+        let mut replayed_game: ElevatorGame = ElevatorGameConfig::new().build_with_seed(5_u64);
+        replayed_game.replay(&log).unwrap();
+        assert_eq!(replayed_game.get_game_state(), game.get_game_state());
+    }
+
+    #[test]
+    fn replay_rejects_another_seed() {
+        let mut game: ElevatorGame = ElevatorGameConfig::new().build_with_seed(5_u64);
+        play(&mut game);
+        let log: ElevatorGameInputLog = ElevatorGameInputLog::from_json(game.export_input_log()).unwrap();
+        let mut other_game: ElevatorGame = ElevatorGameConfig::new().build_with_seed(6_u64);
+        assert!(matches!(
+            other_game.replay(&log),
+            Err(ElevatorGameInputError::InvalidValue { key, .. }) if key == "seed"
+        ));
+        assert_eq!(other_game.time_steps, 0_i32);
+    }
+
+    #[test]
+    fn replay_rejects_logs_longer_than_a_batch() {
+        let mut log: ElevatorGameInputLog = ElevatorGameInputLog::new(5_u64, 0_i32);
+        log.time_steps = MAX_STEPS_PER_BATCH as i32 + 1_i32;
+        let mut game: ElevatorGame = ElevatorGameConfig::new().build_with_seed(5_u64);
+        assert!(matches!(
+            game.replay(&log),
+            Err(ElevatorGameInputError::InvalidValue { key, .. }) if key == "time_steps"
+        ));
+        assert_eq!(game.time_steps, 0_i32);
+    }
+
+    #[test]
+    fn purchase_fills_up_to_max_buys() {
+        //Start two buys short of the limit
+        let mut game: ElevatorGame = game_with_unlocked("efficient_motors");
+        let max_buys: usize = game.upgrades.get("efficient_motors").unwrap().get_max_buys();
+        game.upgrades.get_mut("efficient_motors").unwrap().set_num_buys(max_buys - 2_usize);
+
+        //Going one past the limit is rejected without buying anything
+        let result: Result<Receipt, PurchaseError> = game.purchase("efficient_motors", PurchaseTarget::Building, 3_usize);
+        assert_eq!(result.err(), Some(PurchaseError::MaxedOut));
+        assert_eq!(game.upgrades.get("efficient_motors").unwrap().get_num_buys(), max_buys - 2_usize);
+
+        //Reaching the limit exactly is allowed, and nothing more after it
+        assert!(game.purchase("efficient_motors", PurchaseTarget::Building, 2_usize).is_ok());
+        assert_eq!(game.upgrades.get("efficient_motors").unwrap().get_num_buys(), max_buys);
+        let result: Result<Receipt, PurchaseError> = game.purchase("efficient_motors", PurchaseTarget::Building, 1_usize);
+        assert_eq!(result.err(), Some(PurchaseError::MaxedOut));
+    }
 
-        //Serialize and return the game state JSON string
-        json::stringify(game_state)
+    #[test]
+    fn purchase_rejects_unknown_upgrades_and_quantities() {
+        let mut game: ElevatorGame = game_with_unlocked("efficient_motors");
+        let result: Result<Receipt, PurchaseError> = game.purchase("efficient_motors", PurchaseTarget::Building, 0_usize);
+        assert_eq!(result.err(), Some(PurchaseError::InvalidQuantity { quantity: 0_usize, max: MAX_BULK_QUANTITY }));
+        let result: Result<Receipt, PurchaseError> = game.purchase("efficient_motors", PurchaseTarget::Building, usize::MAX);
+        assert_eq!(result.err(), Some(PurchaseError::InvalidQuantity { quantity: usize::MAX, max: MAX_BULK_QUANTITY }));
+        let result: Result<Receipt, PurchaseError> = game.purchase("time_machine", PurchaseTarget::Building, 1_usize);
+        assert_eq!(result.err(), Some(PurchaseError::UnknownUpgrade { id: "time_machine".to_string() }));
     }
 }
\ No newline at end of file
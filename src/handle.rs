@@ -0,0 +1,218 @@
+//Import standard/external libraries
+use wasm_bindgen::prelude::*;
+use json::object;
+
+//Import source modules
+use crate::config::ElevatorGameConfig;
+use crate::game::{ElevatorGame, MAX_STEPS_PER_BATCH};
+use crate::input::{ElevatorGameInput, ElevatorGameInputError, StopCondition};
+use crate::offline::OfflineSummary;
+use crate::replay::ElevatorGameInputLog;
+use crate::save::ElevatorGameSaveError;
+
+/// # `GameHandle` struct
+///
+/// The `GameHandle` struct is exported to JS as a class wrapping its
+/// own `ElevatorGame`, so that several independent games can be run
+/// side by side.  The memory held by a handle is released from JS by
+/// calling its generated `free` method.
+#[wasm_bindgen]
+pub struct GameHandle {
+    config: ElevatorGameConfig,
+    game: ElevatorGame
+}
+
+#[wasm_bindgen]
+impl GameHandle {
+    /// Initialize a new `GameHandle` wrapping a game with the default
+    /// starting building
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> GameHandle {
+        GameHandle::from(ElevatorGameConfig::new())
+    }
+
+    /// Initialize a new `GameHandle` wrapping a game with the default
+    /// starting building and the given seed.  Games with the same seed
+    /// given the same inputs play out identically.
+    pub fn from_seed(seed: u64) -> GameHandle {
+        let mut config: ElevatorGameConfig = ElevatorGameConfig::new();
+        config.seed = Some(seed);
+        GameHandle::from(config)
+    }
+
+    /// Initialize a new `GameHandle` given a JSON serialized config
+    /// string.  Throws the JSON serialized parse error if the config
+    /// is malformed.
+    pub fn from_config(config: String) -> Result<GameHandle, JsValue> {
+        match ElevatorGameConfig::from_json(config) {
+            Ok(config) => Ok(GameHandle::from(config)),
+            Err(err) => Err(JsValue::from_str(&json::stringify(err.to_json())))
+        }
+    }
+
+    /// Reset the game to the initial state described by its config
+    pub fn reset(&mut self) {
+        self.game = self.config.build();
+    }
+
+    /// Step the game forward given a JSON serialized input string.
+    /// Returns a JSON serialized status object containing the parse
+    /// error if the input was malformed.
+    pub fn step(&mut self, input: String) -> String {
+        //Parse the input JSON string into an input object, returning the
+        //error to the front-end if it is malformed
+        let game_input: ElevatorGameInput = match ElevatorGameInput::from_json(input) {
+            Ok(game_input) => game_input,
+            Err(err) => return json::stringify(object!{ ok: false, error: err.to_json() })
+        };
+
+        //Update the game given the input
+        self.game.update_game_state(game_input);
+        json::stringify(object!{ ok: true })
+    }
+
+    /// Step the game forward up to the given number of time steps, and
+    /// at most `MAX_STEPS_PER_BATCH`, given a JSON serialized input
+    /// string, applied on the first time step, and an optional JSON
+    /// serialized stop condition string.  Returns a JSON serialized
+    /// status object containing the number of time steps run, whether
+    /// the number asked for was capped, and the final game state, or
+    /// the parse error if the input or stop condition was malformed.
+    pub fn step_many(&mut self, num_steps: usize, input: String, stop: Option<String>) -> String {
+        //Parse the input and stop condition, returning the error to the
+        //front-end if either is malformed
+        let game_input: ElevatorGameInput = match ElevatorGameInput::from_json(input) {
+            Ok(game_input) => game_input,
+            Err(err) => return json::stringify(object!{ ok: false, error: err.to_json() })
+        };
+        let stop_condition: Option<StopCondition> = match stop.map(StopCondition::from_json).transpose() {
+            Ok(stop_condition) => stop_condition,
+            Err(err) => return json::stringify(object!{ ok: false, error: err.to_json() })
+        };
+
+        //Make sure a stop condition waiting on a floor names a floor
+        //which exists, since otherwise it could never be met
+        if let Some(StopCondition::FloorFull(Some(floor_index))) = stop_condition {
+            if floor_index >= self.game.get_num_floors() {
+                let err: ElevatorGameInputError = ElevatorGameInputError::InvalidValue {
+                    key: "floor".to_string(),
+                    value: floor_index.to_string()
+                };
+                return json::stringify(object!{ ok: false, error: err.to_json() });
+            }
+        }
+
+        //Step the game and return the final state
+        let num_steps_capped: usize = usize::min(num_steps, MAX_STEPS_PER_BATCH);
+        let num_steps_run: usize = self.game.step_many(num_steps_capped, game_input, stop_condition.as_ref());
+        json::stringify(object!{
+            ok: true,
+            num_steps: num_steps_run,
+            stopped: num_steps_run < num_steps_capped,
+            capped: num_steps > MAX_STEPS_PER_BATCH,
+            max_steps: MAX_STEPS_PER_BATCH,
+            state: self.game.get_game_state_json()
+        })
+    }
+
+    /// Catch up on the given number of seconds spent away from the game,
+    /// as far as the config allows.  Returns the JSON serialized summary
+    /// of what happened while the player was away.
+    pub fn advance_offline(&mut self, elapsed_seconds: f64) -> String {
+        let summary: OfflineSummary = self.game.advance_offline(elapsed_seconds, &self.config.offline);
+        json::stringify(summary.to_json())
+    }
+
+    /// Get the JSON serialized game state
+    pub fn state(&mut self) -> String {
+        self.game.get_game_state()
+    }
+
+    /// Get the JSON serialized changes to the game state since the given
+    /// revision, or the whole state if given revision 0
+    pub fn state_diff(&mut self, since_version: u32) -> String {
+        self.game.get_state_diff(since_version)
+    }
+
+    /// Get the JSON serialized parts of the game state which never change
+    /// during a game
+    pub fn static_metadata(&self) -> String {
+        self.game.get_static_metadata()
+    }
+
+    /// Export the full game state as a JSON serialized save string
+    pub fn export_save(&mut self) -> String {
+        self.game.export_save()
+    }
+
+    /// Load the full game state from a JSON serialized save string.
+    /// Returns a JSON serialized status object containing the error if
+    /// the save failed to load.
+    pub fn import_save(&mut self, save: String) -> String {
+        let result = match self.load_save(save) {
+            Ok(()) => object!{ ok: true },
+            Err(err) => object!{ ok: false, error: err.to_json() }
+        };
+        json::stringify(result)
+    }
+
+    /// Export the input log as a JSON serialized string
+    pub fn export_input_log(&self) -> String {
+        self.game.export_input_log()
+    }
+
+    /// Replace the game with a replay of the given JSON serialized
+    /// input log, initialized from this handle's config and the given
+    /// seed, which must be the seed the log was recorded with.  Returns
+    /// a JSON serialized status object containing the error if the log
+    /// could not be replayed, in which case the game is left untouched.
+    pub fn replay(&mut self, seed: u64, log: String) -> String {
+        //Parse the input log and replay it on a freshly seeded game,
+        //which rejects logs recorded with another seed
+        let result = ElevatorGameInputLog::from_json(log).and_then(|input_log| {
+            let mut game: ElevatorGame = self.config.build_with_seed(seed);
+            game.replay(&input_log)?;
+            Ok(game)
+        });
+
+        //Replace the game with the replayed game
+        match result {
+            Ok(game) => {
+                self.game = game;
+                json::stringify(object!{ ok: true })
+            },
+            Err(err) => json::stringify(object!{ ok: false, error: err.to_json() })
+        }
+    }
+}
+
+impl Default for GameHandle {
+    /// Initialize a new `GameHandle` wrapping a game with the default
+    /// starting building
+    fn default() -> GameHandle {
+        GameHandle::new()
+    }
+}
+
+impl GameHandle {
+    /// Initialize a new `GameHandle` wrapping the game described by
+    /// the given config
+    pub fn from(config: ElevatorGameConfig) -> GameHandle {
+        let game: ElevatorGame = config.build();
+        GameHandle {
+            config: config,
+            game: game
+        }
+    }
+
+    /// Get the config describing the game's initial state
+    pub fn get_config(&self) -> &ElevatorGameConfig {
+        &self.config
+    }
+
+    /// Load the full game state from a JSON serialized save string,
+    /// returning the error if the save failed to load
+    pub fn load_save(&mut self, save: String) -> Result<(), ElevatorGameSaveError> {
+        self.game.import_save(save)
+    }
+}
\ No newline at end of file
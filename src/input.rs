@@ -1,43 +1,592 @@
-use json;
+use std::fmt;
+use json::{object, JsonValue};
+
+//Import source modules
+use crate::controller::ControllerKind;
+use crate::purchase::{PurchaseTarget, UpgradeScope};
+
+/// # `ElevatorGameInputError` enum
+///
+/// The `ElevatorGameInputError` enum describes the ways in which a
+/// JSON serialized input string sent by the front-end can fail to
+/// parse.
+#[derive(Debug)]
+pub enum ElevatorGameInputError {
+    Syntax(json::Error),
+    MissingKey(String),
+    WrongType { key: String, expected: String },
+    InvalidValue { key: String, value: String }
+}
+
+//Implement the ElevatorGameInputError interface
+impl ElevatorGameInputError {
+    /// Serialize the error into a JSON object which can be returned
+    /// to the front-end
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            ElevatorGameInputError::Syntax(err) => object!{
+                kind: "syntax",
+                message: err.to_string()
+            },
+            ElevatorGameInputError::MissingKey(key) => object!{
+                kind: "missing_key",
+                key: key.as_str(),
+                message: self.to_string()
+            },
+            ElevatorGameInputError::WrongType { key, expected } => object!{
+                kind: "wrong_type",
+                key: key.as_str(),
+                expected: expected.as_str(),
+                message: self.to_string()
+            },
+            ElevatorGameInputError::InvalidValue { key, value } => object!{
+                kind: "invalid_value",
+                key: key.as_str(),
+                value: value.as_str(),
+                message: self.to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for ElevatorGameInputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElevatorGameInputError::Syntax(err) => write!(f, "Invalid input syntax: {}", err),
+            ElevatorGameInputError::MissingKey(key) => write!(f, "Input is missing key: {}", key),
+            ElevatorGameInputError::WrongType { key, expected } => write!(f, "Input key {} should be of type {}", key, expected),
+            ElevatorGameInputError::InvalidValue { key, value } => write!(f, "Input key {} has invalid value {}", key, value)
+        }
+    }
+}
+
+impl std::error::Error for ElevatorGameInputError {}
+
+impl From<json::Error> for ElevatorGameInputError {
+    fn from(err: json::Error) -> ElevatorGameInputError {
+        ElevatorGameInputError::Syntax(err)
+    }
+}
+
+/// Get a boolean by key from an input object.  If the key is left out
+/// then the default is returned, or a `MissingKey` error if there is
+/// no default.
+pub fn get_bool(input_object: &JsonValue, key: &str, default: Option<bool>) -> Result<bool, ElevatorGameInputError> {
+    //Fall back to the default if the key is left out
+    if input_object[key].is_null() {
+        return default.ok_or(ElevatorGameInputError::MissingKey(key.to_string()));
+    }
+
+    //Otherwise make sure the value is a boolean
+    input_object[key].as_bool().ok_or(ElevatorGameInputError::WrongType {
+        key: key.to_string(),
+        expected: "bool".to_string()
+    })
+}
+
+/// Get an `f64` by key from an input object.  If the key is left out
+/// then the default is returned, or a `MissingKey` error if there is
+/// no default.
+pub fn get_f64(input_object: &JsonValue, key: &str, default: Option<f64>) -> Result<f64, ElevatorGameInputError> {
+    //Fall back to the default if the key is left out
+    if input_object[key].is_null() {
+        return default.ok_or(ElevatorGameInputError::MissingKey(key.to_string()));
+    }
+
+    //Otherwise make sure the value is a number
+    input_object[key].as_f64().ok_or(ElevatorGameInputError::WrongType {
+        key: key.to_string(),
+        expected: "number".to_string()
+    })
+}
+
+/// Get a `usize` by key from an input object.  If the key is left out
+/// then the default is returned, or a `MissingKey` error if there is
+/// no default.
+pub fn get_usize(input_object: &JsonValue, key: &str, default: Option<usize>) -> Result<usize, ElevatorGameInputError> {
+    //Fall back to the default if the key is left out
+    if input_object[key].is_null() {
+        return default.ok_or(ElevatorGameInputError::MissingKey(key.to_string()));
+    }
+
+    //Otherwise make sure the value is a non-negative integer
+    input_object[key].as_usize().ok_or(ElevatorGameInputError::WrongType {
+        key: key.to_string(),
+        expected: "unsigned integer".to_string()
+    })
+}
+
+/// Get a string by key from an input object.  If the key is left out
+/// then the default is returned, or a `MissingKey` error if there is
+/// no default.
+pub fn get_str<'a>(input_object: &'a JsonValue, key: &str, default: Option<&'a str>) -> Result<&'a str, ElevatorGameInputError> {
+    //Fall back to the default if the key is left out
+    if input_object[key].is_null() {
+        return default.ok_or(ElevatorGameInputError::MissingKey(key.to_string()));
+    }
+
+    //Otherwise make sure the value is a string
+    input_object[key].as_str().ok_or(ElevatorGameInputError::WrongType {
+        key: key.to_string(),
+        expected: "string".to_string()
+    })
+}
+
+/// # `PurchaseQuantity` enum
+///
+/// The `PurchaseQuantity` enum describes how many times the player
+/// asked to buy an upgrade in a single input: a fixed count, where a
+/// count of zero means the upgrade was not asked for, or as many times
+/// as the player can afford.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PurchaseQuantity {
+    Count(usize),
+    Max
+}
+
+//Implement the PurchaseQuantity interface
+impl PurchaseQuantity {
+    /// Check whether the upgrade was not asked for
+    pub fn is_none(&self) -> bool {
+        *self == PurchaseQuantity::Count(0_usize)
+    }
+
+    /// Serialize the quantity into a JSON value, either a count or the
+    /// string `"max"`
+    pub fn to_json(self) -> JsonValue {
+        match self {
+            PurchaseQuantity::Count(count) => count.into(),
+            PurchaseQuantity::Max => "max".into()
+        }
+    }
+}
+
+/// Get a purchase quantity by key from an input object.  The value
+/// may be a boolean for a single buy, a count, or the string `"max"`.
+/// If the key is left out then nothing is bought.
+pub fn get_quantity(input_object: &JsonValue, key: &str) -> Result<PurchaseQuantity, ElevatorGameInputError> {
+    let value: &JsonValue = &input_object[key];
+    if value.is_null() {
+        return Ok(PurchaseQuantity::Count(0_usize));
+    }
+    if let Some(is_requested) = value.as_bool() {
+        return Ok(PurchaseQuantity::Count(if is_requested { 1_usize } else { 0_usize }));
+    }
+    if let Some(count) = value.as_usize() {
+        return Ok(PurchaseQuantity::Count(count));
+    }
+    match value.as_str() {
+        Some("max") => Ok(PurchaseQuantity::Max),
+        Some(other) => Err(ElevatorGameInputError::InvalidValue {
+            key: key.to_string(),
+            value: other.to_string()
+        }),
+        None => Err(ElevatorGameInputError::WrongType {
+            key: key.to_string(),
+            expected: "bool, unsigned integer or \"max\"".to_string()
+        })
+    }
+}
+
+/// Get the target of an upgrade bought by an input object, read from
+/// the `elevator` or `floor` key.  If neither key is given then the
+/// upgrade is bought for the building.
+pub fn get_target(input_object: &JsonValue) -> Result<PurchaseTarget, ElevatorGameInputError> {
+    if !input_object["elevator"].is_null() {
+        return Ok(PurchaseTarget::Elevator(get_usize(input_object, "elevator", None)?));
+    }
+    if !input_object["floor"].is_null() {
+        return Ok(PurchaseTarget::Floor(get_usize(input_object, "floor", None)?));
+    }
+    Ok(PurchaseTarget::Building)
+}
+
+/// # `GameAction` enum
+///
+/// The `GameAction` enum describes a single action the player took
+/// through the front-end during a time step.
+#[derive(Clone)]
+pub enum GameAction {
+    CollectTips,
+    Buy { upgrade_id: String, target: PurchaseTarget, quantity: PurchaseQuantity },
+    SelectController(ControllerKind),
+    Dispatch { elevator: usize, floor: usize },
+    Hold { elevator: usize }
+}
+
+//Implement the GameAction interface
+impl GameAction {
+    /// Serialize the action into a JSON action object
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            GameAction::CollectTips => object!{
+                type: "collect_tips"
+            },
+            GameAction::Buy { upgrade_id, target, quantity } => {
+                let mut action_object = object!{
+                    type: "buy",
+                    upgrade: upgrade_id.as_str(),
+                    qty: quantity.to_json()
+                };
+                if let Some(index) = target.get_index() {
+                    action_object[target.get_scope().as_str()] = index.into();
+                }
+                action_object
+            },
+            GameAction::SelectController(kind) => object!{
+                type: "select_controller",
+                controller: kind.as_str()
+            },
+            GameAction::Dispatch { elevator, floor } => object!{
+                type: "dispatch",
+                elevator: *elevator,
+                floor: *floor
+            },
+            GameAction::Hold { elevator } => object!{
+                type: "hold",
+                elevator: *elevator
+            }
+        }
+    }
+
+    /// Initialize a `GameAction` given a JSON action object, keyed by
+    /// its `type`.
+    ///
+    /// ## Example
+    ///
+    /// ```json
+    /// [
+    ///     { "type": "collect_tips" },
+    ///     { "type": "buy", "upgrade": "append_floor", "qty": 3 },
+    ///     { "type": "buy", "upgrade": "elevator_speed", "elevator": 1, "qty": "max" },
+    ///     { "type": "select_controller", "controller": "look" },
+    ///     { "type": "dispatch", "elevator": 1, "floor": 4 },
+    ///     { "type": "hold", "elevator": 0 }
+    /// ]
+    /// ```
+    pub fn from_json(action_object: &JsonValue) -> Result<GameAction, ElevatorGameInputError> {
+        match get_str(action_object, "type", None)? {
+            "collect_tips" => Ok(GameAction::CollectTips),
+            "buy" => {
+                //Buy the upgrade once unless given a quantity
+                let quantity: PurchaseQuantity = if action_object["qty"].is_null() {
+                    PurchaseQuantity::Count(1_usize)
+                } else {
+                    get_quantity(action_object, "qty")?
+                };
+                Ok(GameAction::Buy {
+                    upgrade_id: get_str(action_object, "upgrade", None)?.to_string(),
+                    target: get_target(action_object)?,
+                    quantity: quantity
+                })
+            },
+            "select_controller" => {
+                let controller: &str = get_str(action_object, "controller", None)?;
+                let kind: ControllerKind = ControllerKind::from_str(controller).ok_or(ElevatorGameInputError::InvalidValue {
+                    key: "controller".to_string(),
+                    value: controller.to_string()
+                })?;
+                Ok(GameAction::SelectController(kind))
+            },
+            "dispatch" => Ok(GameAction::Dispatch {
+                elevator: get_usize(action_object, "elevator", None)?,
+                floor: get_usize(action_object, "floor", None)?
+            }),
+            "hold" => Ok(GameAction::Hold {
+                elevator: get_usize(action_object, "elevator", None)?
+            }),
+            other => Err(ElevatorGameInputError::InvalidValue {
+                key: "type".to_string(),
+                value: other.to_string()
+            })
+        }
+    }
+
+    /// Parse a list of upgrades bought for single elevators or floors by
+    /// key from a version 1 input object, reading each target index from
+    /// the given scope's key.  Each upgrade is bought once unless given
+    /// a `quantity`.  If the key is left out then nothing is bought.
+    fn from_targeted_upgrades(input_object: &JsonValue, key: &str, scope: UpgradeScope) -> Result<Vec<GameAction>, ElevatorGameInputError> {
+        if input_object[key].is_null() {
+            return Ok(Vec::new());
+        }
+        if !input_object[key].is_array() {
+            return Err(ElevatorGameInputError::WrongType {
+                key: key.to_string(),
+                expected: "array".to_string()
+            });
+        }
+        let mut actions: Vec<GameAction> = Vec::new();
+        for upgrade_object in input_object[key].members() {
+            let index: usize = get_usize(upgrade_object, scope.as_str(), None)?;
+            let target: PurchaseTarget = match scope {
+                UpgradeScope::Elevator => PurchaseTarget::Elevator(index),
+                UpgradeScope::Floor => PurchaseTarget::Floor(index),
+                UpgradeScope::Building => PurchaseTarget::Building
+            };
+            let quantity: PurchaseQuantity = if upgrade_object["quantity"].is_null() {
+                PurchaseQuantity::Count(1_usize)
+            } else {
+                get_quantity(upgrade_object, "quantity")?
+            };
+            actions.push(GameAction::Buy {
+                upgrade_id: get_str(upgrade_object, "upgrade", None)?.to_string(),
+                target: target,
+                quantity: quantity
+            });
+        }
+        Ok(actions)
+    }
+}
+
+/// The current version of the input message format.  Input objects
+/// without a `version` or `actions` are read as version 1.
+///
+/// - Version 1: a fixed set of flags, with `collect_tips`, the
+///   building-wide upgrade quantities, `elevator_upgrades`,
+///   `floor_upgrades` and `select_controller`
+/// - Version 2: a list of `actions`, run in order
+pub const INPUT_VERSION: u64 = 2_u64;
 
 /// # `ElevatorGameInput` struct
 ///
 /// The `ElevatorGameInput` struct contains information on the
 /// input provided by the user via the front-end during a time
 /// step.  It is used to update the game state based on user
-/// input.
+/// input, running each of its actions in order.
+#[derive(Clone)]
 pub struct ElevatorGameInput {
-    pub collect_tips: bool,
-    pub append_floor: bool,
-    pub append_elevator: bool,
-    pub add_elevator_capacity: bool,
-    pub add_floor_capacity: bool
+    pub actions: Vec<GameAction>
 }
 
 //Implement the ElevatorGameInput interface
 impl ElevatorGameInput {
     /// Initialize an `ElevatorGameInput` struct explicitly
-    pub fn new(collect_tips: bool, append_floor: bool, append_elevator: bool,
-               add_elevator_capacity: bool, add_floor_capacity: bool) -> ElevatorGameInput {
+    pub fn new(actions: Vec<GameAction>) -> ElevatorGameInput {
         ElevatorGameInput {
-            collect_tips: collect_tips,
-            append_floor: append_floor,
-            append_elevator: append_elevator,
-            add_elevator_capacity: add_elevator_capacity,
-            add_floor_capacity: add_floor_capacity
+            actions: actions
+        }
+    }
+
+    /// Initialize an `ElevatorGameInput` struct with no actions
+    pub fn empty() -> ElevatorGameInput {
+        ElevatorGameInput::new(Vec::new())
+    }
+
+    /// Check whether the input has no actions
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Serialize the input into a JSON input object of the current
+    /// version
+    pub fn to_json(&self) -> JsonValue {
+        object!{
+            version: INPUT_VERSION,
+            actions: self.actions.iter()
+                .map(|action| action.to_json())
+                .collect::<Vec<JsonValue>>()
         }
     }
 
     /// Initialize an `ElevatorGameInput` struct given a JSON
-    /// serialized string containing an input object
-    pub fn from_json(input: String) -> ElevatorGameInput {
-        let input_object = json::parse(&input).unwrap();
-        ElevatorGameInput {
-            collect_tips: input_object["collect_tips"].as_bool().unwrap(),
-            append_floor: input_object["append_floor"].as_bool().unwrap(),
-            append_elevator: input_object["append_elevator"].as_bool().unwrap(),
-            add_elevator_capacity: input_object["add_elevator_capacity"].as_bool().unwrap(),
-            add_floor_capacity: input_object["add_floor_capacity"].as_bool().unwrap()
+    /// serialized string containing an input object.  The input object
+    /// lists `actions` to run in order, see `GameAction::from_json`.
+    /// Version 1 input objects made of flags are still accepted, and are
+    /// read into the list of actions they stand for.
+    ///
+    /// ## Example
+    ///
+    /// ```json
+    /// {
+    ///     "version": 2,
+    ///     "actions": [
+    ///         { "type": "collect_tips" },
+    ///         { "type": "buy", "upgrade": "append_floor", "qty": 3 },
+    ///         { "type": "dispatch", "elevator": 1, "floor": 4 }
+    ///     ]
+    /// }
+    /// ```
+    pub fn from_json(input: String) -> Result<ElevatorGameInput, ElevatorGameInputError> {
+        ElevatorGameInput::from_json_value(&json::parse(&input)?)
+    }
+
+    /// Initialize an `ElevatorGameInput` struct given a parsed JSON
+    /// input object
+    pub fn from_json_value(input_object: &JsonValue) -> Result<ElevatorGameInput, ElevatorGameInputError> {
+        //Make sure the input is an object
+        if !input_object.is_object() {
+            return Err(ElevatorGameInputError::WrongType {
+                key: "input".to_string(),
+                expected: "object".to_string()
+            });
         }
+
+        //Read the input by its version, assuming inputs without a
+        //version are made of flags unless they list actions
+        let default_version: usize = if input_object["actions"].is_null() { 1_usize } else { INPUT_VERSION as usize };
+        match get_usize(input_object, "version", Some(default_version))? {
+            1_usize => ElevatorGameInput::from_flags(input_object),
+            2_usize => {
+                if !input_object["actions"].is_array() {
+                    return Err(ElevatorGameInputError::WrongType {
+                        key: "actions".to_string(),
+                        expected: "array".to_string()
+                    });
+                }
+                let actions: Vec<GameAction> = input_object["actions"].members()
+                    .map(GameAction::from_json)
+                    .collect::<Result<Vec<GameAction>, ElevatorGameInputError>>()?;
+                Ok(ElevatorGameInput::new(actions))
+            },
+            version => Err(ElevatorGameInputError::InvalidValue {
+                key: "version".to_string(),
+                value: version.to_string()
+            })
+        }
+    }
+
+    /// Initialize an `ElevatorGameInput` struct given a version 1 input
+    /// object.  Flags which are left out of the input object default to
+    /// `false`.  The upgrade flags may instead be a number of times to
+    /// buy the upgrade or `"max"` to buy as many as can be afforded.
+    /// The `elevator_upgrades` and `floor_upgrades` list upgrades to buy
+    /// for single elevators and floors by index, and the
+    /// `select_controller` field may name a controller kind to switch
+    /// to.  The flags are read into actions in the order version 1
+    /// inputs were run in.
+    fn from_flags(input_object: &JsonValue) -> Result<ElevatorGameInput, ElevatorGameInputError> {
+        //Collect the tips if asked to
+        let mut actions: Vec<GameAction> = Vec::new();
+        if get_bool(input_object, "collect_tips", Some(false))? {
+            actions.push(GameAction::CollectTips);
+        }
+
+        //Buy each building-wide upgrade as many times as asked for
+        let upgrade_ids: [&str; 4] = ["append_floor", "append_elevator", "add_floor_capacity", "add_elevator_capacity"];
+        for upgrade_id in upgrade_ids.iter() {
+            let quantity: PurchaseQuantity = get_quantity(input_object, upgrade_id)?;
+            if !quantity.is_none() {
+                actions.push(GameAction::Buy {
+                    upgrade_id: upgrade_id.to_string(),
+                    target: PurchaseTarget::Building,
+                    quantity: quantity
+                });
+            }
+        }
+
+        //Buy the upgrades for single elevators and floors
+        actions.extend(GameAction::from_targeted_upgrades(input_object, "elevator_upgrades", UpgradeScope::Elevator)?);
+        actions.extend(GameAction::from_targeted_upgrades(input_object, "floor_upgrades", UpgradeScope::Floor)?);
+
+        //Select the controller, if any
+        if !input_object["select_controller"].is_null() {
+            let controller: &str = get_str(input_object, "select_controller", None)?;
+            actions.push(GameAction::SelectController(ControllerKind::from_str(controller).ok_or(ElevatorGameInputError::InvalidValue {
+                key: "select_controller".to_string(),
+                value: controller.to_string()
+            })?));
+        }
+        Ok(ElevatorGameInput::new(actions))
+    }
+}
+
+/// # `StopCondition` enum
+///
+/// The `StopCondition` enum describes when to stop stepping a game
+/// forward early: once the player's tips, collected or not, reach an
+/// amount, or once a floor is full.  If no floor is given, then any
+/// full floor stops the game.
+#[derive(Clone)]
+pub enum StopCondition {
+    TipsAtLeast(f64),
+    FloorFull(Option<usize>)
+}
+
+//Implement the StopCondition interface
+impl StopCondition {
+    /// Initialize a `StopCondition` given a JSON serialized string
+    /// containing a stop condition object, keyed by its `type`.
+    ///
+    /// ## Example
+    ///
+    /// ```json
+    /// { "type": "tips_at_least", "amount": 500.0 }
+    /// ```
+    pub fn from_json(stop: String) -> Result<StopCondition, ElevatorGameInputError> {
+        StopCondition::from_json_value(&json::parse(&stop)?)
+    }
+
+    /// Initialize a `StopCondition` given a parsed JSON stop condition
+    /// object
+    pub fn from_json_value(stop_object: &JsonValue) -> Result<StopCondition, ElevatorGameInputError> {
+        match get_str(stop_object, "type", None)? {
+            "tips_at_least" => Ok(StopCondition::TipsAtLeast(get_f64(stop_object, "amount", None)?)),
+            "floor_full" => {
+                let floor: Option<usize> = if stop_object["floor"].is_null() {
+                    None
+                } else {
+                    Some(get_usize(stop_object, "floor", None)?)
+                };
+                Ok(StopCondition::FloorFull(floor))
+            },
+            other => Err(ElevatorGameInputError::InvalidValue {
+                key: "type".to_string(),
+                value: other.to_string()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_flags_read_into_actions_in_order() {
+        let input: ElevatorGameInput = ElevatorGameInput::from_json(r#"{
+            "select_controller": "look",
+            "elevator_upgrades": [{ "elevator": 1, "upgrade": "elevator_speed" }],
+            "add_elevator_capacity": "max",
+            "append_floor": 2,
+            "append_elevator": false,
+            "collect_tips": true
+        }"#.to_string()).unwrap();
+        assert_eq!(input.actions.len(), 5_usize);
+        assert!(matches!(input.actions[0], GameAction::CollectTips));
+        assert!(matches!(&input.actions[1], GameAction::Buy { upgrade_id, target: PurchaseTarget::Building, quantity: PurchaseQuantity::Count(2_usize) }
+            if upgrade_id == "append_floor"));
+        assert!(matches!(&input.actions[2], GameAction::Buy { upgrade_id, target: PurchaseTarget::Building, quantity: PurchaseQuantity::Max }
+            if upgrade_id == "add_elevator_capacity"));
+        assert!(matches!(&input.actions[3], GameAction::Buy { upgrade_id, target: PurchaseTarget::Elevator(1_usize), quantity: PurchaseQuantity::Count(1_usize) }
+            if upgrade_id == "elevator_speed"));
+        assert!(matches!(input.actions[4], GameAction::SelectController(ControllerKind::Look)));
+    }
+
+    #[test]
+    fn empty_input_is_version_1() {
+        let input: ElevatorGameInput = ElevatorGameInput::from_json("{}".to_string()).unwrap();
+        assert!(input.actions.is_empty());
+    }
+
+    #[test]
+    fn version_2_reads_its_action_list() {
+        let input: ElevatorGameInput = ElevatorGameInput::from_json(r#"{
+            "version": 2,
+            "actions": [{ "type": "collect_tips" }, { "type": "dispatch", "elevator": 1, "floor": 4 }]
+        }"#.to_string()).unwrap();
+        assert_eq!(input.actions.len(), 2_usize);
+        assert!(matches!(input.actions[0], GameAction::CollectTips));
+        assert!(matches!(input.actions[1], GameAction::Dispatch { elevator: 1_usize, floor: 4_usize }));
+    }
+
+    #[test]
+    fn unknown_versions_and_missing_actions_are_rejected() {
+        let too_new: Result<ElevatorGameInput, ElevatorGameInputError> = ElevatorGameInput::from_json(r#"{ "version": 3, "actions": [] }"#.to_string());
+        let no_actions: Result<ElevatorGameInput, ElevatorGameInputError> = ElevatorGameInput::from_json(r#"{ "version": 2 }"#.to_string());
+        let bad_flag: Result<ElevatorGameInput, ElevatorGameInputError> = ElevatorGameInput::from_json(r#"{ "append_floor": "lots" }"#.to_string());
+        assert!(matches!(too_new, Err(ElevatorGameInputError::InvalidValue { .. })));
+        assert!(matches!(no_actions, Err(ElevatorGameInputError::WrongType { .. })));
+        assert!(matches!(bad_flag, Err(ElevatorGameInputError::InvalidValue { .. })));
     }
 }
\ No newline at end of file
@@ -1,57 +1,257 @@
+//Struct fields are always initialized explicitly, even from variables
+//of the same name
+#![allow(clippy::redundant_field_names)]
+
+mod billing;
+mod config;
+mod context;
+mod controller;
+mod cost;
+mod diff;
 mod game;
+mod handle;
 mod input;
+mod offline;
+mod purchase;
+mod replay;
+mod save;
 mod upgrade;
 
 //Import source modules
-use crate::game::ElevatorGame;
-use crate::input::ElevatorGameInput;
-use crate::upgrade::ElevatorGameUpgrades;
+use crate::config::ElevatorGameConfig;
+pub use crate::handle::GameHandle;
 
 //Import standard/imported libraries
 use wasm_bindgen::prelude::*;
 use lazy_static::lazy_static;
-use std::sync::Mutex;
-use rand::Rng;
-use rand::SeedableRng;
-use rand::rngs::StdRng;
-use elevate_lib::building::Building;
-use elevate_lib::controller::{RandomController};
+use json::object;
+use std::panic;
+use std::sync::{Arc, Mutex, Once, PoisonError, TryLockError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// The number of single steps between automatic snapshots of the game
+const SNAPSHOT_INTERVAL: usize = 100_usize;
 
 lazy_static! {
-  static ref GAME: Mutex<ElevatorGame> = Mutex::new(
-    ElevatorGame::from(
-      Box::new(
-        RandomController::from_building(
-          Building::from(
-            4_usize,
-            2_usize,
-            0.5_f64,
-            100_usize,
-            10_usize,
-            5.0_f64,
-            2.5_f64,
-            0.5_f64
-          )
-        )
-      ),
-      ElevatorGameUpgrades::new(),
-      StdRng::from_seed(rand::thread_rng().gen())
-    )
-  );
-}
-
-#[wasm_bindgen]
-pub fn update_game_state(input: String) {
-  //Parse the input JSON string into an input object
-  let game_input: ElevatorGameInput = ElevatorGameInput::from_json(input);
-
-  //Acquire lock for game state and update given input
-  let mut game = GAME.lock().unwrap();
-  game.update_game_state(game_input);
+  static ref GAME: Mutex<Arc<Mutex<GameHandle>>> = Mutex::new(Arc::new(Mutex::new(GameHandle::new())));
+  static ref LAST_CONFIG: Mutex<ElevatorGameConfig> = Mutex::new(ElevatorGameConfig::new());
+  static ref LAST_SAVE: Mutex<Option<String>> = Mutex::new(None);
+  static ref LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// Whether the game panicked since it was last recovered
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+/// The number of single steps since the game was last snapshotted
+static STEPS_SINCE_SNAPSHOT: AtomicUsize = AtomicUsize::new(0_usize);
+
+/// Record an error so that the host can read it via `get_last_error`
+fn set_last_error(message: String) {
+  let mut last_error = LAST_ERROR.lock().unwrap_or_else(PoisonError::into_inner);
+  *last_error = Some(message);
+}
+
+/// Install a panic hook which records the panic message as the last
+/// error and flags the game as panicked.  On wasm32 a panic aborts
+/// instead of unwinding, so the game lock is never poisoned; it is
+/// left locked forever instead, and the flag is how that is told apart
+/// from the lock merely being held.
+fn install_panic_hook() {
+  static HOOK: Once = Once::new();
+  HOOK.call_once(|| {
+    panic::set_hook(Box::new(|info| {
+      PANICKED.store(true, Ordering::SeqCst);
+      set_last_error(format!("Game panicked: {}", info));
+    }));
+  });
+}
+
+/// Build a new game to replace one which panicked, from the config of
+/// the last game started, restored from the last snapshot if there is
+/// one
+fn recover_game() -> GameHandle {
+  //Start the game afresh from its config
+  let config: ElevatorGameConfig = LAST_CONFIG.lock().unwrap_or_else(PoisonError::into_inner).clone();
+  let mut game: GameHandle = GameHandle::from(config);
+
+  //Restore the last snapshot on top of it if there is one
+  let last_save = LAST_SAVE.lock().unwrap_or_else(PoisonError::into_inner).clone();
+  let restored: bool = match last_save {
+    Some(save) => game.load_save(save).is_ok(),
+    None => false
+  };
+
+  //Record the recovery
+  let recovery: &str = if restored { "restored from the last snapshot" } else { "reset" };
+  let cause: String = LAST_ERROR.lock().unwrap_or_else(PoisonError::into_inner).take()
+    .unwrap_or("Game panicked".to_string());
+  set_last_error(format!("{}; the game was {}", cause, recovery));
+  game
+}
+
+/// Get the default game handle.  If a previous call panicked while
+/// holding its lock, then the lock is either poisoned or, on wasm32,
+/// still held, so the handle is abandoned and replaced with a game
+/// recovered from the last snapshot.
+fn current_game() -> Arc<Mutex<GameHandle>> {
+  let mut game_slot = GAME.lock().unwrap_or_else(PoisonError::into_inner);
+  let is_stale: bool = match game_slot.try_lock() {
+    Ok(_) => false,
+    Err(TryLockError::Poisoned(_)) => true,
+    Err(TryLockError::WouldBlock) => PANICKED.load(Ordering::SeqCst)
+  };
+  if is_stale {
+    *game_slot = Arc::new(Mutex::new(recover_game()));
+    PANICKED.store(false, Ordering::SeqCst);
+  }
+  game_slot.clone()
+}
+
+/// Run the given closure on the default game handle
+fn with_game<T>(f: impl FnOnce(&mut GameHandle) -> T) -> T {
+  install_panic_hook();
+  let game = current_game();
+  let mut game = game.lock().unwrap_or_else(PoisonError::into_inner);
+  f(&mut game)
+}
+
+/// Remember a snapshot of the game as the last good state to recover
+/// from.  A save has no side effects on the game, so snapshots do not
+/// change how it plays out.
+fn snapshot(game: &mut GameHandle) {
+  let save: String = game.export_save();
+  set_last_save(Some(save));
+}
+
+/// Count the given number of single steps towards the next automatic
+/// snapshot, taking it once enough have been run
+fn snapshot_if_due(game: &mut GameHandle, num_steps: usize) {
+  if STEPS_SINCE_SNAPSHOT.fetch_add(num_steps, Ordering::SeqCst) + num_steps >= SNAPSHOT_INTERVAL {
+    STEPS_SINCE_SNAPSHOT.store(0_usize, Ordering::SeqCst);
+    snapshot(game);
+  }
+}
+
+/// Remember a save string as the last good save to recover from
+fn set_last_save(save: Option<String>) {
+  STEPS_SINCE_SNAPSHOT.store(0_usize, Ordering::SeqCst);
+  let mut last_save = LAST_SAVE.lock().unwrap_or_else(PoisonError::into_inner);
+  *last_save = save;
+}
+
+/// Remember the config of a newly started game to recover from
+fn set_last_config(config: ElevatorGameConfig) {
+  let mut last_config = LAST_CONFIG.lock().unwrap_or_else(PoisonError::into_inner);
+  *last_config = config;
+}
+
+#[wasm_bindgen]
+pub fn update_game_state(input: String) -> String {
+  with_game(|game| {
+    let status: String = game.step(input);
+    snapshot_if_due(game, 1_usize);
+    status
+  })
+}
+
+#[wasm_bindgen]
+pub fn step_many(num_steps: usize, input: String, stop: Option<String>) -> String {
+  with_game(|game| {
+    let summary: String = game.step_many(num_steps, input, stop);
+    snapshot(game);
+    summary
+  })
+}
+
+#[wasm_bindgen]
+pub fn advance_offline(elapsed_seconds: f64) -> String {
+  with_game(|game| {
+    let summary: String = game.advance_offline(elapsed_seconds);
+    snapshot(game);
+    summary
+  })
 }
 
 #[wasm_bindgen]
 pub fn get_game_state() -> String {
-  let mut game = GAME.lock().unwrap();
-  game.get_game_state()
+  with_game(|game| game.state())
+}
+
+#[wasm_bindgen]
+pub fn get_state_diff(since_version: u32) -> String {
+  with_game(|game| game.state_diff(since_version))
+}
+
+#[wasm_bindgen]
+pub fn get_static_metadata() -> String {
+  with_game(|game| game.static_metadata())
+}
+
+#[wasm_bindgen]
+pub fn export_save() -> String {
+  with_game(|game| {
+    let save: String = game.export_save();
+    set_last_save(Some(save.clone()));
+    save
+  })
+}
+
+#[wasm_bindgen]
+pub fn import_save(save: String) -> String {
+  //Load the save into the default game handle
+  let result = with_game(|game| match game.load_save(save.clone()) {
+    Ok(()) => {
+      set_last_save(Some(save));
+      object!{ ok: true }
+    },
+    Err(err) => object!{ ok: false, error: err.to_json() }
+  });
+  json::stringify(result)
+}
+
+#[wasm_bindgen]
+pub fn export_input_log() -> String {
+  with_game(|game| game.export_input_log())
+}
+
+#[wasm_bindgen]
+pub fn replay(seed: u64, log: String) -> String {
+  with_game(|game| {
+    let result: String = game.replay(seed, log);
+    snapshot(game);
+    result
+  })
+}
+
+#[wasm_bindgen]
+pub fn new_game(config: String) -> String {
+  //Parse the config JSON string, returning the error to the front-end
+  //if it is malformed
+  let game_config: ElevatorGameConfig = match ElevatorGameConfig::from_json(config) {
+    Ok(game_config) => game_config,
+    Err(err) => return json::stringify(object!{ ok: false, error: err.to_json() })
+  };
+
+  //Replace the default game handle with the configured game
+  set_last_config(game_config.clone());
+  with_game(|game| {
+    *game = GameHandle::from(game_config);
+    snapshot(game);
+  });
+  json::stringify(object!{ ok: true })
+}
+
+#[wasm_bindgen]
+pub fn reset_game() {
+  //Reset the default game handle to its config
+  with_game(|game| {
+    game.reset();
+    snapshot(game);
+  });
+}
+
+#[wasm_bindgen]
+pub fn get_last_error() -> Option<String> {
+  let mut last_error = LAST_ERROR.lock().unwrap_or_else(PoisonError::into_inner);
+  last_error.take()
 }
\ No newline at end of file
@@ -0,0 +1,110 @@
+//Import standard/external libraries
+use json::{object, JsonValue};
+
+//Import source modules
+use crate::game::MAX_STEPS_PER_BATCH;
+use crate::input::{self, ElevatorGameInputError};
+
+/// # `OfflineProgress` struct
+///
+/// The `OfflineProgress` struct describes how a game catches up on the
+/// time the player spent away from it: how many time steps each second
+/// away is worth, and the most time steps caught up on at once, which
+/// never exceeds `MAX_STEPS_PER_BATCH`.
+#[derive(Clone)]
+pub struct OfflineProgress {
+    pub steps_per_second: f64,
+    pub max_steps: usize
+}
+
+//Implement the OfflineProgress interface
+impl OfflineProgress {
+    /// Initialize the `OfflineProgress` describing the default catch-up,
+    /// with a time step per second for up to `MAX_STEPS_PER_BATCH` time
+    /// steps
+    pub fn new() -> OfflineProgress {
+        OfflineProgress {
+            steps_per_second: 1.0_f64,
+            max_steps: MAX_STEPS_PER_BATCH
+        }
+    }
+
+    /// Initialize an `OfflineProgress` given a JSON offline config
+    /// object.  Fields which are left out fall back to the default
+    /// catch-up, and a `max_steps` above `MAX_STEPS_PER_BATCH` is lowered
+    /// to it.
+    ///
+    /// ## Example
+    ///
+    /// ```json
+    /// {
+    ///     "steps_per_second": 4.0,
+    ///     "max_steps": 10000
+    /// }
+    /// ```
+    pub fn from_json(config: &JsonValue) -> Result<OfflineProgress, ElevatorGameInputError> {
+        //Read each field, falling back to the default catch-up
+        let default: OfflineProgress = OfflineProgress::new();
+        let offline: OfflineProgress = OfflineProgress {
+            steps_per_second: input::get_f64(config, "steps_per_second", Some(default.steps_per_second))?,
+            max_steps: usize::min(
+                input::get_usize(config, "max_steps", Some(default.max_steps))?,
+                MAX_STEPS_PER_BATCH
+            )
+        };
+
+        //Make sure time away is worth a sensible number of time steps
+        if !offline.steps_per_second.is_finite() || offline.steps_per_second < 0.0_f64 {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "steps_per_second".to_string(),
+                value: offline.steps_per_second.to_string()
+            });
+        }
+        Ok(offline)
+    }
+
+    /// Get the number of time steps to catch up on for the given number
+    /// of seconds away, along with whether it was capped
+    pub fn get_num_steps(&self, elapsed_seconds: f64) -> (usize, bool) {
+        //Nothing is caught up on for a negative or unknown time away
+        if !elapsed_seconds.is_finite() || elapsed_seconds <= 0.0_f64 {
+            return (0_usize, false);
+        }
+        let max_steps: usize = usize::min(self.max_steps, MAX_STEPS_PER_BATCH);
+        let num_steps: f64 = (elapsed_seconds * self.steps_per_second).floor();
+        if num_steps >= max_steps as f64 {
+            return (max_steps, num_steps > max_steps as f64);
+        }
+        (num_steps as usize, false)
+    }
+}
+
+/// # `OfflineSummary` struct
+///
+/// The `OfflineSummary` struct sums up what happened while a game was
+/// caught up on the time the player spent away from it.
+pub struct OfflineSummary {
+    pub elapsed_seconds: f64,
+    pub num_steps: usize,
+    pub is_capped: bool,
+    pub tips_earned: f64,
+    pub people_served: usize,
+    pub energy_spent: f64,
+    pub energy_billed: f64
+}
+
+//Implement the OfflineSummary interface
+impl OfflineSummary {
+    /// Serialize the summary into a JSON object
+    pub fn to_json(&self) -> JsonValue {
+        object!{
+            elapsed_seconds: self.elapsed_seconds,
+            num_steps: self.num_steps,
+            capped: self.is_capped,
+            tips_earned: self.tips_earned,
+            people_served: self.people_served,
+            energy_spent: self.energy_spent,
+            energy_billed: self.energy_billed
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,197 @@
+//Import standard/external libraries
+use std::fmt;
+use json::{object, JsonValue};
+
+/// # `PurchaseError` enum
+///
+/// The `PurchaseError` enum describes the ways in which buying an
+/// upgrade can be rejected.  A rejected purchase leaves the game
+/// untouched.
+#[derive(Clone, PartialEq, Debug)]
+pub enum PurchaseError {
+    InsufficientFunds { needed: f64, have: f64 },
+    MaxedOut,
+    Locked,
+    UnknownUpgrade { id: String },
+    InvalidQuantity { quantity: usize, max: usize },
+    InvalidTarget
+}
+
+//Implement the PurchaseError interface
+impl PurchaseError {
+    /// Serialize the error into a JSON object which can be returned
+    /// to the front-end
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            PurchaseError::InsufficientFunds { needed, have } => object!{
+                kind: "insufficient_funds",
+                needed: *needed,
+                have: *have,
+                message: self.to_string()
+            },
+            PurchaseError::MaxedOut => object!{
+                kind: "maxed_out",
+                message: self.to_string()
+            },
+            PurchaseError::Locked => object!{
+                kind: "locked",
+                message: self.to_string()
+            },
+            PurchaseError::UnknownUpgrade { id } => object!{
+                kind: "unknown_upgrade",
+                id: id.as_str(),
+                message: self.to_string()
+            },
+            PurchaseError::InvalidQuantity { quantity, max } => object!{
+                kind: "invalid_quantity",
+                quantity: *quantity,
+                max: *max,
+                message: self.to_string()
+            },
+            PurchaseError::InvalidTarget => object!{
+                kind: "invalid_target",
+                message: self.to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for PurchaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PurchaseError::InsufficientFunds { needed, have } => write!(f, "Not enough tips: needed {}, have {}", needed, have),
+            PurchaseError::MaxedOut => write!(f, "Upgrade cannot be bought any more times"),
+            PurchaseError::Locked => write!(f, "Upgrade is locked"),
+            PurchaseError::UnknownUpgrade { id } => write!(f, "No upgrade with id: {}", id),
+            PurchaseError::InvalidQuantity { quantity, max } => write!(f, "Cannot buy an upgrade {} times at once, only 1 to {}", quantity, max),
+            PurchaseError::InvalidTarget => write!(f, "Upgrade cannot be bought for that target")
+        }
+    }
+}
+
+impl std::error::Error for PurchaseError {}
+
+/// # `UpgradeScope` enum
+///
+/// The `UpgradeScope` enum describes what an upgrade is bought for:
+/// the whole building, a single elevator or a single floor.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UpgradeScope {
+    Building,
+    Elevator,
+    Floor
+}
+
+//Implement the UpgradeScope interface
+impl UpgradeScope {
+    /// Get the name of the upgrade scope
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UpgradeScope::Building => "building",
+            UpgradeScope::Elevator => "elevator",
+            UpgradeScope::Floor => "floor"
+        }
+    }
+}
+
+/// # `PurchaseTarget` enum
+///
+/// The `PurchaseTarget` enum describes what a purchase is made for:
+/// the whole building, the elevator at an index or the floor at an
+/// index.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PurchaseTarget {
+    Building,
+    Elevator(usize),
+    Floor(usize)
+}
+
+//Implement the PurchaseTarget interface
+impl PurchaseTarget {
+    /// Get the scope of upgrades which can be bought for the target
+    pub fn get_scope(&self) -> UpgradeScope {
+        match self {
+            PurchaseTarget::Building => UpgradeScope::Building,
+            PurchaseTarget::Elevator(_) => UpgradeScope::Elevator,
+            PurchaseTarget::Floor(_) => UpgradeScope::Floor
+        }
+    }
+
+    /// Get the index of the elevator or floor the purchase is made
+    /// for, if any
+    pub fn get_index(&self) -> Option<usize> {
+        match self {
+            PurchaseTarget::Building => None,
+            PurchaseTarget::Elevator(index) | PurchaseTarget::Floor(index) => Some(*index)
+        }
+    }
+
+    /// Serialize the target into a JSON object
+    pub fn to_json(self) -> JsonValue {
+        object!{
+            scope: self.get_scope().as_str(),
+            index: self.get_index()
+        }
+    }
+}
+
+/// # `Receipt` struct
+///
+/// The `Receipt` struct records a successful purchase: the upgrade
+/// bought, what it was bought for, how many times it was bought and
+/// the total tips spent.
+#[derive(Clone)]
+pub struct Receipt {
+    pub upgrade_id: String,
+    pub target: PurchaseTarget,
+    pub quantity: usize,
+    pub cost: f64
+}
+
+//Implement the Receipt interface
+impl Receipt {
+    /// Serialize the receipt into a JSON object
+    pub fn to_json(&self) -> JsonValue {
+        object!{
+            upgrade: self.upgrade_id.as_str(),
+            target: self.target.to_json(),
+            quantity: self.quantity,
+            cost: self.cost
+        }
+    }
+}
+
+/// # `PurchaseRecord` struct
+///
+/// The `PurchaseRecord` struct records the outcome of a purchase the
+/// player attempted, so that the front-end can explain why a click
+/// did or did not do anything.
+pub struct PurchaseRecord {
+    pub time_step: i32,
+    pub upgrade_id: String,
+    pub target: PurchaseTarget,
+    pub quantity: usize,
+    pub result: Result<Receipt, PurchaseError>
+}
+
+//Implement the PurchaseRecord interface
+impl PurchaseRecord {
+    /// Serialize the record into a JSON object
+    pub fn to_json(&self) -> JsonValue {
+        match &self.result {
+            Ok(receipt) => object!{
+                time_step: self.time_step,
+                ok: true,
+                receipt: receipt.to_json()
+            },
+            Err(err) => object!{
+                time_step: self.time_step,
+                ok: false,
+                upgrade: self.upgrade_id.as_str(),
+                target: self.target.to_json(),
+                quantity: self.quantity,
+                error: err.to_json()
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,160 @@
+//Import standard/external libraries
+use json::{object, JsonValue};
+
+//Import source modules
+use crate::input::{self, ElevatorGameInput, ElevatorGameInputError};
+
+/// The current version of the input log format
+pub const INPUT_LOG_VERSION: u64 = 1_u64;
+
+/// # `ElevatorGameInputLog` struct
+///
+/// The `ElevatorGameInputLog` struct records every non-empty input an
+/// `ElevatorGame` was given along with the time step it was given at.
+/// Together with the game seed this is enough to replay a session
+/// deterministically.
+pub struct ElevatorGameInputLog {
+    pub seed: u64,
+    pub start_time_step: i32,
+    pub time_steps: i32,
+    pub inputs: Vec<(i32, ElevatorGameInput)>
+}
+
+//Implement the ElevatorGameInputLog interface
+impl ElevatorGameInputLog {
+    /// Initialize an empty `ElevatorGameInputLog` for a game with the
+    /// given seed, starting at the given time step
+    pub fn new(seed: u64, start_time_step: i32) -> ElevatorGameInputLog {
+        ElevatorGameInputLog {
+            seed: seed,
+            start_time_step: start_time_step,
+            time_steps: start_time_step,
+            inputs: Vec::new()
+        }
+    }
+
+    /// Record the input given at a time step.  Empty inputs are only
+    /// counted towards the length of the log.
+    pub fn record_input(&mut self, time_step: i32, input: &ElevatorGameInput) {
+        if !input.is_empty() {
+            self.inputs.push((time_step, input.clone()));
+        }
+        self.time_steps = time_step + 1_i32;
+    }
+
+    /// Serialize the input log into a JSON object
+    pub fn to_json(&self) -> JsonValue {
+        //Initialize the input log object
+        let mut log_object = object!{
+            version: INPUT_LOG_VERSION,
+            seed: self.seed.to_string(),
+            start_time_step: self.start_time_step,
+            time_steps: self.time_steps,
+            inputs: []
+        };
+
+        //Append each recorded input
+        for (time_step, input) in self.inputs.iter() {
+            let _ = log_object["inputs"].push(
+                object!{
+                    time_step: *time_step,
+                    input: input.to_json()
+                }
+            );
+        }
+
+        //Return the input log object
+        log_object
+    }
+
+    /// Initialize an `ElevatorGameInputLog` given a JSON serialized
+    /// string containing an input log object
+    pub fn from_json(log: String) -> Result<ElevatorGameInputLog, ElevatorGameInputError> {
+        //Parse the input log string and make sure it is an object
+        let log_object: JsonValue = json::parse(&log)?;
+        if !log_object.is_object() {
+            return Err(ElevatorGameInputError::WrongType {
+                key: "log".to_string(),
+                expected: "object".to_string()
+            });
+        }
+
+        //Make sure the input log version is supported
+        let version: usize = input::get_usize(&log_object, "version", None)?;
+        if version == 0_usize || version as u64 > INPUT_LOG_VERSION {
+            return Err(ElevatorGameInputError::InvalidValue {
+                key: "version".to_string(),
+                value: version.to_string()
+            });
+        }
+
+        //Read the seed and the time step range
+        let seed_str: &str = input::get_str(&log_object, "seed", None)?;
+        let seed: u64 = seed_str.parse::<u64>().map_err(|_| ElevatorGameInputError::InvalidValue {
+            key: "seed".to_string(),
+            value: seed_str.to_string()
+        })?;
+        let mut input_log: ElevatorGameInputLog = ElevatorGameInputLog::new(
+            seed,
+            get_time_step(&log_object, "start_time_step")?
+        );
+        input_log.time_steps = get_time_step(&log_object, "time_steps")?;
+
+        //Read each recorded input
+        for entry in log_object["inputs"].members() {
+            input_log.inputs.push((
+                get_time_step(entry, "time_step")?,
+                ElevatorGameInput::from_json_value(&entry["input"])?
+            ));
+        }
+
+        //Return the input log
+        Ok(input_log)
+    }
+}
+
+/// Get a time step by key from an input log object, making sure it fits
+/// in the game's time step counter
+fn get_time_step(log_object: &JsonValue, key: &str) -> Result<i32, ElevatorGameInputError> {
+    let time_step: usize = input::get_usize(log_object, key, None)?;
+    i32::try_from(time_step).map_err(|_| ElevatorGameInputError::InvalidValue {
+        key: key.to_string(),
+        value: time_step.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::GameAction;
+
+    #[test]
+    fn log_round_trips() {
+        //Record a log with an input partway through
+        let mut log: ElevatorGameInputLog = ElevatorGameInputLog::new(42_u64, 0_i32);
+        let mut input: ElevatorGameInput = ElevatorGameInput::empty();
+        input.actions.push(GameAction::CollectTips);
+        log.record_input(0_i32, &ElevatorGameInput::empty());
+        log.record_input(1_i32, &input);
+        log.record_input(2_i32, &ElevatorGameInput::empty());
+
+        //Reading the exported log gives the same log back
+        let restored: ElevatorGameInputLog = ElevatorGameInputLog::from_json(json::stringify(log.to_json())).unwrap();
+        assert_eq!(restored.seed, 42_u64);
+        assert_eq!(restored.time_steps, 3_i32);
+        assert_eq!(restored.inputs.len(), 1_usize);
+        assert_eq!(restored.to_json().dump(), log.to_json().dump());
+    }
+
+    #[test]
+    fn log_rejects_time_steps_out_of_range() {
+        let log: String = format!(
+            r#"{{ "version": 1, "seed": "42", "start_time_step": 0, "time_steps": {}, "inputs": [] }}"#,
+            i32::MAX as u64 + 1_u64
+        );
+        assert!(matches!(
+            ElevatorGameInputLog::from_json(log),
+            Err(ElevatorGameInputError::InvalidValue { key, .. }) if key == "time_steps"
+        ));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,477 @@
+//Import standard/external libraries
+use std::fmt;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand_chacha::ChaCha12Rng;
+use json::{object, JsonValue};
+use elevate_lib::building::Building;
+use elevate_lib::person::Person;
+use elevate_lib::people::People;
+use elevate_lib::floor::Floor;
+use elevate_lib::elevator::Elevator;
+
+/// The current version of the save format.  Bump this whenever the
+/// save layout changes and add a migration step to `migrate`.
+pub const SAVE_VERSION: u64 = 1_u64;
+
+/// # `ElevatorGameSaveError` enum
+///
+/// The `ElevatorGameSaveError` enum describes the ways in which a
+/// save string can fail to load.
+#[derive(Debug)]
+pub enum ElevatorGameSaveError {
+    Syntax(json::Error),
+    MissingKey(String),
+    WrongType(String),
+    InvalidValue(String),
+    UnsupportedVersion(u64)
+}
+
+//Implement the ElevatorGameSaveError interface
+impl ElevatorGameSaveError {
+    /// Serialize the error into a JSON object which can be returned
+    /// to the front-end
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            ElevatorGameSaveError::Syntax(err) => object!{
+                kind: "syntax",
+                message: err.to_string()
+            },
+            ElevatorGameSaveError::MissingKey(key) => object!{
+                kind: "missing_key",
+                key: key.as_str(),
+                message: self.to_string()
+            },
+            ElevatorGameSaveError::WrongType(key) => object!{
+                kind: "wrong_type",
+                key: key.as_str(),
+                message: self.to_string()
+            },
+            ElevatorGameSaveError::InvalidValue(key) => object!{
+                kind: "invalid_value",
+                key: key.as_str(),
+                message: self.to_string()
+            },
+            ElevatorGameSaveError::UnsupportedVersion(version) => object!{
+                kind: "unsupported_version",
+                version: *version,
+                message: self.to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for ElevatorGameSaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ElevatorGameSaveError::Syntax(err) => write!(f, "Invalid save syntax: {}", err),
+            ElevatorGameSaveError::MissingKey(key) => write!(f, "Save is missing key: {}", key),
+            ElevatorGameSaveError::WrongType(key) => write!(f, "Save has wrong type for key: {}", key),
+            ElevatorGameSaveError::InvalidValue(key) => write!(f, "Save has invalid value for key: {}", key),
+            ElevatorGameSaveError::UnsupportedVersion(version) => write!(f, "Unsupported save version: {}", version)
+        }
+    }
+}
+
+impl std::error::Error for ElevatorGameSaveError {}
+
+impl From<json::Error> for ElevatorGameSaveError {
+    fn from(err: json::Error) -> ElevatorGameSaveError {
+        ElevatorGameSaveError::Syntax(err)
+    }
+}
+
+/// Migrate a parsed save object to the current `SAVE_VERSION`.  Saves
+/// of any other version are rejected, since there is no older layout
+/// to upgrade from yet.  Add a migration step here whenever the
+/// version is bumped.
+pub fn migrate(save: JsonValue) -> Result<JsonValue, ElevatorGameSaveError> {
+    let version: u64 = get_u64(&save, "version")?;
+    if version != SAVE_VERSION {
+        return Err(ElevatorGameSaveError::UnsupportedVersion(version));
+    }
+    Ok(save)
+}
+
+/// Get a required `JsonValue` by key from a save object
+pub fn get<'a>(value: &'a JsonValue, key: &str) -> Result<&'a JsonValue, ElevatorGameSaveError> {
+    if !value.has_key(key) {
+        return Err(ElevatorGameSaveError::MissingKey(key.to_string()));
+    }
+    Ok(&value[key])
+}
+
+/// Get a required `f64` by key from a save object
+pub fn get_f64(value: &JsonValue, key: &str) -> Result<f64, ElevatorGameSaveError> {
+    get(value, key)?.as_f64().ok_or(ElevatorGameSaveError::WrongType(key.to_string()))
+}
+
+/// Get a required `u64` by key from a save object
+pub fn get_u64(value: &JsonValue, key: &str) -> Result<u64, ElevatorGameSaveError> {
+    get(value, key)?.as_u64().ok_or(ElevatorGameSaveError::WrongType(key.to_string()))
+}
+
+/// Get a required `u64` by key from a save object, stored as a string
+/// since JS numbers cannot hold every u64
+pub fn get_u64_str(value: &JsonValue, key: &str) -> Result<u64, ElevatorGameSaveError> {
+    get(value, key)?.as_str()
+        .and_then(|value_str| value_str.parse::<u64>().ok())
+        .ok_or(ElevatorGameSaveError::WrongType(key.to_string()))
+}
+
+/// Get a required `usize` by key from a save object
+pub fn get_usize(value: &JsonValue, key: &str) -> Result<usize, ElevatorGameSaveError> {
+    get(value, key)?.as_usize().ok_or(ElevatorGameSaveError::WrongType(key.to_string()))
+}
+
+/// Get an optional `usize` by key from a save object, falling back to
+/// the default if the key is left out
+pub fn get_usize_or(value: &JsonValue, key: &str, default: usize) -> Result<usize, ElevatorGameSaveError> {
+    if !value.has_key(key) {
+        return Ok(default);
+    }
+    get_usize(value, key)
+}
+
+/// Get an array of `usize` by key from a save object, or an empty
+/// array if the key is left out
+pub fn get_usize_array_or_empty(value: &JsonValue, key: &str) -> Result<Vec<usize>, ElevatorGameSaveError> {
+    if !value.has_key(key) {
+        return Ok(Vec::new());
+    }
+    get(value, key)?.members()
+        .map(|member| member.as_usize().ok_or(ElevatorGameSaveError::WrongType(key.to_string())))
+        .collect()
+}
+
+/// Get a required `f64` by key from a save object, making sure it lies
+/// within the given bounds, each of which is inclusive if flagged
+pub fn get_f64_within(value: &JsonValue, key: &str, min: (f64, bool), max: (f64, bool)) -> Result<f64, ElevatorGameSaveError> {
+    let number: f64 = get_f64(value, key)?;
+    let is_above_min: bool = if min.1 { number >= min.0 } else { number > min.0 };
+    let is_below_max: bool = if max.1 { number <= max.0 } else { number < max.0 };
+    if !is_above_min || !is_below_max {
+        return Err(ElevatorGameSaveError::InvalidValue(key.to_string()));
+    }
+    Ok(number)
+}
+
+/// Get a required array of optional `usize` by key from a save object,
+/// where `null` stands for no value
+pub fn get_optional_usize_array(value: &JsonValue, key: &str) -> Result<Vec<Option<usize>>, ElevatorGameSaveError> {
+    get(value, key)?.members()
+        .map(|member| match member {
+            JsonValue::Null => Ok(None),
+            _ => member.as_usize().map(Some).ok_or(ElevatorGameSaveError::WrongType(key.to_string()))
+        })
+        .collect()
+}
+
+/// Get a required `i32` by key from a save object
+pub fn get_i32(value: &JsonValue, key: &str) -> Result<i32, ElevatorGameSaveError> {
+    get(value, key)?.as_i32().ok_or(ElevatorGameSaveError::WrongType(key.to_string()))
+}
+
+/// Get a required `bool` by key from a save object
+pub fn get_bool(value: &JsonValue, key: &str) -> Result<bool, ElevatorGameSaveError> {
+    get(value, key)?.as_bool().ok_or(ElevatorGameSaveError::WrongType(key.to_string()))
+}
+
+/// Get a required, non-empty array by key from a save object
+pub fn get_array<'a>(value: &'a JsonValue, key: &str) -> Result<&'a JsonValue, ElevatorGameSaveError> {
+    let array: &JsonValue = get(value, key)?;
+    if !array.is_array() || array.is_empty() {
+        return Err(ElevatorGameSaveError::WrongType(key.to_string()));
+    }
+    Ok(array)
+}
+
+/// Serialize the state of a `ChaCha12Rng` into a save object, as its
+/// key and its position in the stream.  The position is stored as a
+/// string since JS numbers cannot hold every u128.
+pub fn rng_to_json(rng: &ChaCha12Rng) -> JsonValue {
+    object!{
+        key: rng.get_seed().to_vec(),
+        word_pos: rng.get_word_pos().to_string()
+    }
+}
+
+/// Deserialize the state of a `ChaCha12Rng` from a save object
+pub fn rng_from_json(value: &JsonValue) -> Result<ChaCha12Rng, ElevatorGameSaveError> {
+    //Read the key
+    let key_array: &JsonValue = get_array(value, "key")?;
+    let mut key: [u8; 32] = [0_u8; 32];
+    if key_array.len() != key.len() {
+        return Err(ElevatorGameSaveError::WrongType("key".to_string()));
+    }
+    for (byte, byte_value) in key.iter_mut().zip(key_array.members()) {
+        *byte = byte_value.as_u8().ok_or(ElevatorGameSaveError::WrongType("key".to_string()))?;
+    }
+
+    //Read the position in the stream
+    let word_pos: u128 = get(value, "word_pos")?.as_str()
+        .and_then(|word_pos| word_pos.parse::<u128>().ok())
+        .ok_or(ElevatorGameSaveError::WrongType("word_pos".to_string()))?;
+
+    //Restore the RNG
+    let mut rng: ChaCha12Rng = ChaCha12Rng::from_seed(key);
+    rng.set_word_pos(word_pos);
+    Ok(rng)
+}
+
+/// The probability of leaving the building elevate-lib gives everyone
+/// arriving at it.  elevate-lib keeps it private, so it is mirrored
+/// here to restore the people on a floor who are not going anywhere.
+const RESIDENT_P_OUT: f64 = 0.05_f64;
+
+/// The probability of tipping on the way out elevate-lib gives everyone
+/// arriving at the building, mirrored for the same reason
+const RESIDENT_P_TIP: f64 = 0.5_f64;
+
+/// Initialize a `Person` on the given floor who is not going anywhere.
+/// elevate-lib draws a destination floor for every new person, which
+/// is overwritten straight after, so it is drawn from a throwaway RNG.
+fn new_person(p_out: f64, p_tip: f64, floor_on: usize, num_floors: usize) -> Person {
+    let mut person: Person = Person::from(p_out, p_tip, num_floors, &mut StdRng::seed_from_u64(0_u64));
+    person.floor_on = floor_on;
+    person.floor_to = floor_on;
+    person
+}
+
+/// Serialize a `Person` into a save object
+fn person_to_json(person: &Person) -> JsonValue {
+    object!{
+        floor_on: person.floor_on,
+        floor_to: person.floor_to,
+        is_leaving: person.is_leaving,
+        wait_time: person.wait_time,
+        p_out: person.p_out,
+        p_tip: person.p_tip
+    }
+}
+
+/// Deserialize a `Person` from a save object
+fn person_from_json(value: &JsonValue, num_floors: usize) -> Result<Person, ElevatorGameSaveError> {
+    //Initialize the person, whose probabilities must be ones elevate-lib
+    //accepts, then overwrite the rest of their state
+    let mut person: Person = new_person(
+        get_f64_within(value, "p_out", (0.0_f64, true), (1.0_f64, true))?,
+        get_f64_within(value, "p_tip", (0.0_f64, true), (1.0_f64, true))?,
+        0_usize,
+        num_floors
+    );
+    person.floor_on = get_usize(value, "floor_on")?;
+    person.floor_to = get_usize(value, "floor_to")?;
+    if person.floor_on >= num_floors || person.floor_to >= num_floors {
+        return Err(ElevatorGameSaveError::InvalidValue("people".to_string()));
+    }
+    person.is_leaving = get_bool(value, "is_leaving")?;
+    person.wait_time = get_usize(value, "wait_time")?;
+    Ok(person)
+}
+
+/// Serialize a list of people into a save array
+fn people_to_json(people: &[Person]) -> JsonValue {
+    let mut people_array = JsonValue::new_array();
+    for person in people.iter() {
+        let _ = people_array.push(person_to_json(person));
+    }
+    people_array
+}
+
+/// Deserialize a list of people from a save array
+fn people_from_json(value: &JsonValue, num_floors: usize) -> Result<Vec<Person>, ElevatorGameSaveError> {
+    let mut people: Vec<Person> = Vec::new();
+    for person in get(value, "people")?.members() {
+        people.push(person_from_json(person, num_floors)?);
+    }
+    Ok(people)
+}
+
+/// Get the people on a floor who are waiting for an elevator or leaving
+/// the building, along with the number of people on it who are neither.
+/// elevate-lib keeps the people on a floor private, so the first two
+/// groups are flushed off a clone of the floor through its public API,
+/// in order.  Everyone left is on their destination floor with no wait
+/// time, since wait times are reset on getting off an elevator, and
+/// has the probabilities elevate-lib gives everyone arriving, so they
+/// are only counted.  Only the order between the groups is lost, which
+/// elevate-lib never looks at.
+fn get_floor_people(floor: &Floor) -> (Vec<Person>, usize) {
+    let mut floor: Floor = floor.clone();
+    let mut people: Vec<Person> = floor.flush_people_entering_elevator(usize::MAX);
+    people.extend(floor.flush_people_leaving_floor());
+    (people, floor.get_num_people())
+}
+
+/// Serialize a `Building` into a save object, including its floors,
+/// elevators and the people on each of them.  The arrival rate is left
+/// out, since elevate-lib keeps it private and it never changes during
+/// a game.  So is the number of people the average wait time is taken
+/// over, which elevate-lib also keeps private.
+pub fn building_to_json(building: &Building) -> JsonValue {
+    //Initialize the building save object
+    let mut building_save = object!{
+        avg_wait_time: building.avg_wait_time,
+        avg_energy: building.avg_energy,
+        tot_tips: building.tot_tips,
+        floors: [],
+        elevators: []
+    };
+
+    //Append the save state for each floor
+    for floor in building.floors.iter() {
+        let (people, num_residents) = get_floor_people(floor);
+        let _ = building_save["floors"].push(
+            object!{
+                capacity: floor.capacity,
+                dest_prob: floor.dest_prob,
+                people: people_to_json(&people),
+                residents: num_residents
+            }
+        );
+    }
+
+    //Append the save state for each elevator
+    for elevator in building.elevators.iter() {
+        let _ = building_save["elevators"].push(
+            object!{
+                capacity: elevator.capacity,
+                energy_up: elevator.energy_up,
+                energy_down: elevator.energy_down,
+                energy_coef: elevator.energy_coef,
+                floor_on: elevator.floor_on,
+                moving_up: elevator.moving_up,
+                stopped: elevator.stopped,
+                people: people_to_json(&elevator.people)
+            }
+        );
+    }
+
+    //Return the building save object
+    building_save
+}
+
+/// Deserialize a `Building` from a save object given the arrival rate
+/// it was built with.  elevate-lib keeps the number of people its
+/// average wait time is taken over private, so the restored building
+/// starts that count afresh: the saved average carries on, but the
+/// next person to get off an elevator sets it to their own wait time.
+pub fn building_from_json(value: &JsonValue, p_in: f64) -> Result<Building, ElevatorGameSaveError> {
+    //Read the floor and elevator arrays
+    let floors: &JsonValue = get_array(value, "floors")?;
+    let elevators: &JsonValue = get_array(value, "elevators")?;
+    let num_floors: usize = floors.len();
+
+    //Restore each floor, making sure none of its people were turned away
+    let mut restored_floors: Vec<Floor> = Vec::new();
+    for (floor_index, floor_save) in floors.members().enumerate() {
+        let mut floor: Floor = Floor::new(get_usize(floor_save, "capacity")?);
+        floor.dest_prob = get_f64(floor_save, "dest_prob")?;
+        let mut people: Vec<Person> = people_from_json(floor_save, num_floors)?;
+        let num_residents: usize = get_usize(floor_save, "residents")?;
+        if num_residents > floor.capacity {
+            return Err(ElevatorGameSaveError::InvalidValue("residents".to_string()));
+        }
+        let resident: Person = new_person(RESIDENT_P_OUT, RESIDENT_P_TIP, floor_index, num_floors);
+        people.extend(vec![resident; num_residents]);
+        let num_people: usize = people.len();
+        floor.extend(people);
+        if floor.get_num_people() != num_people {
+            return Err(ElevatorGameSaveError::InvalidValue("people".to_string()));
+        }
+        restored_floors.push(floor);
+    }
+
+    //Restore each elevator
+    let mut restored_elevators: Vec<Elevator> = Vec::new();
+    for elevator_save in elevators.members() {
+        let mut elevator: Elevator = Elevator::from(
+            get_usize(elevator_save, "capacity")?,
+            get_f64(elevator_save, "energy_up")?,
+            get_f64(elevator_save, "energy_down")?,
+            get_f64(elevator_save, "energy_coef")?
+        );
+        elevator.floor_on = get_usize(elevator_save, "floor_on")?;
+        elevator.moving_up = get_bool(elevator_save, "moving_up")?;
+        elevator.stopped = get_bool(elevator_save, "stopped")?;
+        elevator.people = people_from_json(elevator_save, num_floors)?;
+        if elevator.floor_on >= num_floors || elevator.people.len() > elevator.capacity {
+            return Err(ElevatorGameSaveError::InvalidValue("elevators".to_string()));
+        }
+        restored_elevators.push(elevator);
+    }
+
+    //Initialize the building with its arrival rate, then restore the rest
+    let mut building: Building = Building::from(
+        num_floors, 0_usize, p_in, 0_usize, 0_usize, 0.0_f64, 0.0_f64, 0.0_f64
+    );
+    building.floors = restored_floors;
+    building.elevators = restored_elevators;
+    building.avg_wait_time = get_f64(value, "avg_wait_time")?;
+    building.avg_energy = get_f64(value, "avg_energy")?;
+    building.tot_tips = get_f64(value, "tot_tips")?;
+
+    //Return the restored building
+    Ok(building)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+    use elevate_lib::floors::Floors;
+
+    #[test]
+    fn building_round_trips_with_its_people() {
+        //Run a building for a while so that people are waiting, riding,
+        //leaving and staying on its floors
+        let mut rng: StdRng = StdRng::seed_from_u64(3_u64);
+        let mut building: Building = Building::from(
+            5_usize, 2_usize, 0.8_f64, 100_usize, 10_usize, 5.0_f64, 2.5_f64, 0.5_f64
+        );
+        for step in 0_i32..200_i32 {
+            building.gen_people_arriving(&mut rng);
+            building.gen_people_leaving(&mut rng);
+            building.flush_and_update_tips(&mut rng);
+            building.exchange_people_on_elevator();
+            for elevator in building.elevators.iter_mut() {
+                elevator.stopped = step % 3_i32 == 0_i32;
+                elevator.moving_up = elevator.floor_on == 0_usize || (elevator.moving_up && elevator.floor_on < 4_usize);
+                elevator.update_floor();
+            }
+            building.increment_wait_times();
+            building.update_dest_probabilities();
+        }
+
+        //Saving the restored building gives the same floors and
+        //elevators back.  JSON numbers may round the last digit of the
+        //averages.
+        let saved: JsonValue = building_to_json(&building);
+        let restored: Building = building_from_json(&saved, 0.8_f64).unwrap();
+        let resaved: JsonValue = building_to_json(&restored);
+        assert_eq!(resaved["floors"].dump(), saved["floors"].dump());
+        assert_eq!(resaved["elevators"].dump(), saved["elevators"].dump());
+        assert!((restored.avg_wait_time - building.avg_wait_time).abs() < 1e-9_f64);
+        let num_people = |building: &Building| building.floors.iter()
+            .map(|floor| floor.get_num_people())
+            .collect::<Vec<usize>>();
+        assert_eq!(num_people(&restored), num_people(&building));
+    }
+
+    #[test]
+    fn migrate_rejects_other_versions() {
+        let too_old: JsonValue = object!{ version: 0_u64 };
+        let too_new: JsonValue = object!{ version: SAVE_VERSION + 1_u64 };
+        assert!(matches!(migrate(too_old), Err(ElevatorGameSaveError::UnsupportedVersion(0_u64))));
+        assert!(matches!(migrate(too_new), Err(ElevatorGameSaveError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn rng_round_trips_mid_stream() {
+        let mut rng: ChaCha12Rng = ChaCha12Rng::seed_from_u64(7_u64);
+        let _ = rng.next_u64();
+        let mut restored: ChaCha12Rng = rng_from_json(&rng_to_json(&rng)).unwrap();
+        assert_eq!(restored.next_u64(), rng.next_u64());
+    }
+}
\ No newline at end of file
@@ -1,27 +1,132 @@
+//Import standard/external libraries
+use json::{object, JsonValue};
+use elevate_lib::building::Building;
+use elevate_lib::floors::Floors;
+
+//Import source modules
+use crate::config::BaseStats;
+use crate::context::GameContext;
+use crate::controller::ControllerKind;
+use crate::cost::CostCurve;
+use crate::input::{self, ElevatorGameInputError};
+use crate::purchase::{PurchaseError, PurchaseTarget, UpgradeScope};
+
+/// The most buys of a single upgrade which are previewed or made in
+/// one bulk purchase, so that free or very cheap upgrades which can be
+/// bought without limit do not buy forever
+pub const MAX_BULK_QUANTITY: usize = 1000_usize;
+
+/// The bulk quantities whose total cost is reported for each upgrade
+/// in the game state
+pub const BULK_QUANTITIES: [usize; 2] = [10_usize, 100_usize];
+
 /// # `ElevatorGameUpgrades` struct
 ///
-/// The `ElevatorGameUpgrades` struct stores each elevator game
-/// upgrade and provides an interface through which one may interact
-/// with the game's upgrades.
+/// The `ElevatorGameUpgrades` struct is the registry of elevator game
+/// upgrades, keyed by id, and provides an interface through which one
+/// may interact with the game's upgrades.
 pub struct ElevatorGameUpgrades {
-    pub collect_tips: CollectTipsUpgrade,
-    pub append_floor: AppendFloorUpgrade,
-    pub append_elevator: AppendElevatorUpgrade,
-    pub add_floor_capacity: AddFloorCapacityUpgrade,
-    pub add_elevator_capacity: AddElevatorCapacityUpgrade
+    upgrades: Vec<Upgrade>
 }
 
 impl ElevatorGameUpgrades {
-    /// Initialize the `ElevatorGameUpgrades` struct
+    /// Initialize the `ElevatorGameUpgrades` registry with the default
+    /// upgrade definitions
     pub fn new() -> ElevatorGameUpgrades {
+        ElevatorGameUpgrades::from(UpgradeDefinition::defaults())
+    }
+
+    /// Initialize the `ElevatorGameUpgrades` registry given a list of
+    /// upgrade definitions
+    pub fn from(definitions: Vec<UpgradeDefinition>) -> ElevatorGameUpgrades {
         ElevatorGameUpgrades {
-            collect_tips: CollectTipsUpgrade::new(),
-            append_floor: AppendFloorUpgrade::new(10_f64, 1.5_f64),
-            append_elevator: AppendElevatorUpgrade::new(100_f64, 1.9_f64),
-            add_floor_capacity: AddFloorCapacityUpgrade::new(10_f64, 1.1_f64),
-            add_elevator_capacity: AddElevatorCapacityUpgrade::new(10_f64, 1.1_f64)
+            upgrades: definitions.into_iter().map(Upgrade::new).collect()
         }
     }
+
+    /// Get an upgrade by id
+    pub fn get(&self, id: &str) -> Option<&Upgrade> {
+        self.upgrades.iter().find(|upgrade| upgrade.get_id() == id)
+    }
+
+    /// Get a mutable reference to an upgrade by id
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Upgrade> {
+        self.upgrades.iter_mut().find(|upgrade| upgrade.get_id() == id)
+    }
+
+    /// Get the upgrade which unlocks the given controller kind, if the
+    /// controller kind can be bought
+    pub fn get_controller_mut(&mut self, kind: ControllerKind) -> Option<&mut Upgrade> {
+        self.upgrades.iter_mut().find(|upgrade| upgrade.get_effect() == &UpgradeEffect::SetController(kind))
+    }
+
+    /// Get how many more floors per time step the elevator at the given
+    /// index travels thanks to its speed upgrades
+    pub fn get_speed_bonus(&self, index: usize) -> usize {
+        self.upgrades.iter()
+            .map(|upgrade| match upgrade.get_effect() {
+                UpgradeEffect::ElevatorSpeed(amount) => amount * upgrade.get_target_buys(index),
+                _ => 0_usize
+            })
+            .sum()
+    }
+
+    /// Get how many fewer time steps the elevator at the given index
+    /// holds its doors open thanks to its dwell time upgrades
+    pub fn get_dwell_reduction(&self, index: usize) -> usize {
+        self.upgrades.iter()
+            .map(|upgrade| match upgrade.get_effect() {
+                UpgradeEffect::ElevatorDwellTime(amount) => amount * upgrade.get_target_buys(index),
+                _ => 0_usize
+            })
+            .sum()
+    }
+
+    /// Get the stats of a newly built floor or elevator, being the given
+    /// stats of the configured floors and elevators with every building
+    /// wide capacity and energy upgrade bought so far applied
+    pub fn get_new_stats(&self, base_stats: &BaseStats) -> BaseStats {
+        let mut new_stats: BaseStats = base_stats.clone();
+        for upgrade in self.upgrades.iter() {
+            let num_buys: usize = upgrade.get_num_buys();
+            match upgrade.get_effect() {
+                UpgradeEffect::AddFloorCapacity(amount) => new_stats.floor_capacity += amount * num_buys,
+                UpgradeEffect::AddElevatorCapacity(amount) => new_stats.elevator_capacity += amount * num_buys,
+                UpgradeEffect::ScaleEnergyUp(factor) => new_stats.energy_up *= factor.powi(num_buys as i32),
+                UpgradeEffect::ScaleEnergyDown(factor) => new_stats.energy_down *= factor.powi(num_buys as i32),
+                UpgradeEffect::ScaleEnergyCoef(factor) => new_stats.energy_coef *= factor.powi(num_buys as i32),
+                _ => {}
+            }
+        }
+        new_stats
+    }
+
+    /// Unlock every locked upgrade whose prerequisites are met by the
+    /// given building and the upgrades bought so far.  Once unlocked,
+    /// an upgrade stays unlocked even if its prerequisites stop being
+    /// met.
+    pub fn update_unlocks(&mut self, building: &Building) {
+        //Find the upgrades to unlock before unlocking any of them
+        let unlocked: Vec<bool> = self.upgrades.iter()
+            .map(|upgrade| upgrade.is_unlocked() || upgrade.definition.prerequisites.iter()
+                .all(|prerequisite| prerequisite.is_met(building, self)))
+            .collect();
+
+        //Unlock them
+        for (upgrade, is_unlocked) in self.upgrades.iter_mut().zip(unlocked) {
+            upgrade.unlocked = is_unlocked;
+        }
+    }
+
+    /// Iterate over the upgrades in the registry
+    pub fn iter(&self) -> std::slice::Iter<'_, Upgrade> {
+        self.upgrades.iter()
+    }
+
+    /// Iterate mutably over the upgrades in the registry
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Upgrade> {
+        self.upgrades.iter_mut()
+    }
 }
 
 /// # `ElevatorGameUpgrade` trait
@@ -31,378 +136,890 @@ impl ElevatorGameUpgrades {
 pub trait ElevatorGameUpgrade {
     fn get_cost(&self) -> f64;
 
-    fn is_enough(&self, money: f64) -> bool;
+    fn cost_of_next(&self, n: usize) -> f64;
+
+    /// Get the total cost of buying the upgrade the given number of
+    /// times in a row
+    fn total_cost(&self, quantity: usize) -> f64 {
+        (0_usize..quantity).map(|n| self.cost_of_next(n)).sum()
+    }
+
+    /// Get the largest number of times in a row the upgrade can be
+    /// bought with the given amount, up to `MAX_BULK_QUANTITY`
+    fn max_affordable(&self, money: f64) -> usize {
+        //Never count past the remaining buys or the bulk limit
+        let remaining_buys: usize = self.get_max_buys().saturating_sub(self.get_num_buys());
+        let limit: usize = usize::min(remaining_buys, MAX_BULK_QUANTITY);
+
+        //Add up the cost of each buy until the money runs out
+        let mut total: f64 = 0.0_f64;
+        let mut quantity: usize = 0_usize;
+        while quantity < limit {
+            total += self.cost_of_next(quantity);
+            if total > money {
+                break;
+            }
+            quantity += 1_usize;
+        }
+        quantity
+    }
+
+    /// Check if the given amount is less than the cost of the upgrade
+    fn is_enough(&self, money: f64) -> bool {
+        money >= self.get_cost()
+    }
 
     fn get_max_buys(&self) -> usize;
 
+    fn get_num_buys(&self) -> usize;
+
+    fn set_num_buys(&mut self, num_buys: usize);
+
     fn get_name(&self) -> &str;
 
     fn get_description(&self) -> &str;
 
-    fn buy(&mut self) -> f64;
+    fn is_unlocked(&self) -> bool;
+
+    fn get_scope(&self) -> UpgradeScope;
+
+    fn buy(&mut self) -> Result<f64, PurchaseError>;
+
+    fn apply(&self, building: &mut Building, context: &mut GameContext);
 }
 
-/// # `CollectTipsUpgrade` struct
-///
-/// The `CollectTipsUpgrade` struct is an upgrade for collecting tips
-/// from the player's building.  It is constantly available throughout
-/// the game.
-pub struct CollectTipsUpgrade {
-    name: String,
-    description: String
+/// Get the largest number of times in a row the upgrade can be bought
+/// with the given amount, which is none while it is locked
+pub fn get_max_affordable(upgrade: &dyn ElevatorGameUpgrade, money: f64) -> usize {
+    if !upgrade.is_unlocked() {
+        return 0_usize;
+    }
+    upgrade.max_affordable(money)
 }
 
-impl CollectTipsUpgrade {
-    /// Initialize an `CollectTipsUpgrade` struct
-    pub fn new() -> CollectTipsUpgrade {
-        //Set the name of the upgrade and the description
-        let name = "Collect Tips".to_string();
-        let description = "Collect the tips accumulated by your building".to_string();
-
-        //Initialize and return the CollectTipsUpgrade
-        CollectTipsUpgrade {
-            name: name,
-            description: description
-        }
+/// Get the total cost of each bulk quantity of the upgrade, or null if
+/// it cannot be bought that many more times
+fn get_bulk_costs(upgrade: &dyn ElevatorGameUpgrade) -> JsonValue {
+    let mut bulk_costs: JsonValue = JsonValue::new_object();
+    let remaining_buys: usize = upgrade.get_max_buys().saturating_sub(upgrade.get_num_buys());
+    for quantity in BULK_QUANTITIES.iter() {
+        bulk_costs[quantity.to_string().as_str()] = if *quantity <= remaining_buys {
+            upgrade.total_cost(*quantity).into()
+        } else {
+            JsonValue::Null
+        };
     }
+    bulk_costs
 }
 
-impl ElevatorGameUpgrade for CollectTipsUpgrade {
-    /// Get the cost of the upgrade
-    fn get_cost(&self) -> f64 {
-        0.0_f64
+/// Serialize the state of an upgrade into a JSON object for the shop,
+/// given the amount of money the player has to spend.  Upgrades which
+/// can be bought without limit have a `null` max buys, and locked
+/// upgrades are never affordable.  Upgrades bought for single elevators
+/// or floors are priced and limited per target, so they only report
+/// their total buys across every target here, and their pricing,
+/// including their bulk costs, is reported with each target's state
+/// instead.
+pub fn get_upgrade_state(upgrade: &dyn ElevatorGameUpgrade, money: f64) -> JsonValue {
+    let max_buys: Option<usize> = match upgrade.get_max_buys() {
+        usize::MAX => None,
+        max_buys => Some(max_buys)
+    };
+    let locked: bool = !upgrade.is_unlocked();
+    let mut upgrade_state = object!{
+        name: upgrade.get_name(),
+        description: upgrade.get_description(),
+        scope: upgrade.get_scope().as_str(),
+        num_buys: upgrade.get_num_buys(),
+        max_buys: max_buys,
+        locked: locked
+    };
+    if upgrade.get_scope() != UpgradeScope::Building {
+        return upgrade_state;
     }
 
-    /// Check if the given amount is less than the cost of the upgrade
-    fn is_enough(&self, money: f64) -> bool {
-        true
+    //Add the pricing of upgrades bought for the whole building
+    let maxed_out: bool = upgrade.get_num_buys() >= upgrade.get_max_buys();
+    let max_affordable: usize = get_max_affordable(upgrade, money);
+    upgrade_state["cost"] = upgrade.get_cost().into();
+    upgrade_state["affordable"] = (!locked && !maxed_out && upgrade.is_enough(money)).into();
+    upgrade_state["maxed_out"] = maxed_out.into();
+    upgrade_state["max_affordable"] = max_affordable.into();
+    upgrade_state["max_affordable_cost"] = upgrade.total_cost(max_affordable).into();
+    upgrade_state["bulk_costs"] = get_bulk_costs(upgrade);
+    upgrade_state
+}
+
+/// Serialize the state of an upgrade bought for a single target into
+/// a compact JSON object, given the amount of money the player has to
+/// spend
+pub fn get_target_state(upgrade: &dyn ElevatorGameUpgrade, money: f64) -> JsonValue {
+    let maxed_out: bool = upgrade.get_num_buys() >= upgrade.get_max_buys();
+    let locked: bool = !upgrade.is_unlocked();
+    let max_affordable: usize = get_max_affordable(upgrade, money);
+    object!{
+        level: upgrade.get_num_buys(),
+        cost: upgrade.get_cost(),
+        affordable: !locked && !maxed_out && upgrade.is_enough(money),
+        maxed_out: maxed_out,
+        locked: locked,
+        max_affordable: max_affordable,
+        max_affordable_cost: upgrade.total_cost(max_affordable),
+        bulk_costs: get_bulk_costs(upgrade)
     }
+}
 
-    /// Get the maximum number of floors one can buy
-    fn get_max_buys(&self) -> usize {
-        usize::MAX
+/// # `UpgradeEffect` enum
+///
+/// The `UpgradeEffect` enum describes what buying an upgrade does to
+/// the game, along with how much it does.
+///
+/// - `CollectTips`: move the building's tips into the player's tips
+/// - `AppendFloor`: add this many floors, built with the configured
+///   floor capacity plus every floor capacity upgrade bought so far
+/// - `AppendElevator`: add this many elevators, built with the
+///   configured elevator stats plus every elevator capacity and energy
+///   upgrade bought so far
+/// - `AddFloorCapacity`: add this much capacity to every floor
+/// - `AddElevatorCapacity`: add this much capacity to every elevator
+/// - `SetController`: switch to this kind of controller
+/// - `ElevatorCapacity`: add this much capacity to one elevator
+/// - `ElevatorSpeed`: let one elevator travel this many more floors
+///   per time step
+/// - `ElevatorEnergyCoef`: multiply one elevator's energy coefficient
+///   by this factor
+/// - `ElevatorDwellTime`: hold one elevator's doors open for this many
+///   fewer time steps
+/// - `FloorCapacity`: add this much capacity to one floor
+/// - `ScaleEnergyUp`: multiply every elevator's energy spent moving up
+///   by this factor
+/// - `ScaleEnergyDown`: multiply every elevator's energy spent moving
+///   down by this factor
+/// - `ScaleEnergyCoef`: multiply every elevator's energy coefficient
+///   by this factor
+#[derive(Clone, PartialEq, Debug)]
+pub enum UpgradeEffect {
+    CollectTips,
+    AppendFloor(usize),
+    AppendElevator(usize),
+    AddFloorCapacity(usize),
+    AddElevatorCapacity(usize),
+    SetController(ControllerKind),
+    ElevatorCapacity(usize),
+    ElevatorSpeed(usize),
+    ElevatorEnergyCoef(f64),
+    ElevatorDwellTime(usize),
+    FloorCapacity(usize),
+    ScaleEnergyUp(f64),
+    ScaleEnergyDown(f64),
+    ScaleEnergyCoef(f64)
+}
+
+impl UpgradeEffect {
+    /// Get the scope of the effect, which determines what the upgrade
+    /// is bought for
+    pub fn get_scope(&self) -> UpgradeScope {
+        match self {
+            UpgradeEffect::ElevatorCapacity(_) | UpgradeEffect::ElevatorSpeed(_) |
+            UpgradeEffect::ElevatorEnergyCoef(_) | UpgradeEffect::ElevatorDwellTime(_) => UpgradeScope::Elevator,
+            UpgradeEffect::FloorCapacity(_) => UpgradeScope::Floor,
+            _ => UpgradeScope::Building
+        }
     }
 
-    /// Get the name of the upgrade
-    fn get_name(&self) -> &str {
-        &self.name
+    /// Apply the effect to the building and the rest of the game
+    pub fn apply(&self, building: &mut Building, context: &mut GameContext) {
+        match self {
+            //Collect the tips from the building
+            UpgradeEffect::CollectTips => {
+                context.tips += building.collect_tips();
+            },
+
+            //Add floors or elevators to the building, built with the
+            //stats every new floor or elevator gets rather than those of
+            //a floor or elevator which may have its own upgrades
+            UpgradeEffect::AppendFloor(count) => {
+                for _ in 0_usize..*count {
+                    building.append_floor(context.new_stats.floor_capacity);
+                }
+            },
+            UpgradeEffect::AppendElevator(count) => {
+                let new_stats: &BaseStats = &context.new_stats;
+                for _ in 0_usize..*count {
+                    building.append_elevator(new_stats.elevator_capacity, new_stats.energy_up,
+                                             new_stats.energy_down, new_stats.energy_coef);
+                }
+            },
+
+            //Add capacity to the building's floors or elevators
+            UpgradeEffect::AddFloorCapacity(amount) => {
+                for floor in building.floors.iter_mut() {
+                    floor.capacity += amount;
+                }
+            },
+            UpgradeEffect::AddElevatorCapacity(amount) => {
+                for elevator in building.elevators.iter_mut() {
+                    elevator.capacity += amount;
+                }
+            },
+
+            //Switch to the bought controller
+            UpgradeEffect::SetController(kind) => {
+                context.controller_kind = *kind;
+            },
+
+            //Upgrade the elevator the upgrade was bought for.  Its speed
+            //and dwell time are read from its upgrade levels each step.
+            UpgradeEffect::ElevatorCapacity(amount) => {
+                if let PurchaseTarget::Elevator(index) = context.target {
+                    building.elevators[index].capacity += amount;
+                }
+            },
+            UpgradeEffect::ElevatorEnergyCoef(factor) => {
+                if let PurchaseTarget::Elevator(index) = context.target {
+                    building.elevators[index].energy_coef *= factor;
+                }
+            },
+            UpgradeEffect::ElevatorSpeed(_) | UpgradeEffect::ElevatorDwellTime(_) => {},
+
+            //Upgrade the floor the upgrade was bought for
+            UpgradeEffect::FloorCapacity(amount) => {
+                if let PurchaseTarget::Floor(index) = context.target {
+                    building.floors[index].capacity += amount;
+                }
+            },
+
+            //Make every elevator more energy efficient
+            UpgradeEffect::ScaleEnergyUp(factor) => {
+                for elevator in building.elevators.iter_mut() {
+                    elevator.energy_up *= factor;
+                }
+            },
+            UpgradeEffect::ScaleEnergyDown(factor) => {
+                for elevator in building.elevators.iter_mut() {
+                    elevator.energy_down *= factor;
+                }
+            },
+            UpgradeEffect::ScaleEnergyCoef(factor) => {
+                for elevator in building.elevators.iter_mut() {
+                    elevator.energy_coef *= factor;
+                }
+            }
+        }
     }
 
-    /// Get the description of the upgrade
-    fn get_description(&self) -> &str {
-        &self.description
+    /// Get a copy of the effect with its magnitude replaced, for the
+    /// effects which have one
+    pub fn with_amount(&self, amount: usize) -> UpgradeEffect {
+        match self {
+            UpgradeEffect::AppendFloor(_) => UpgradeEffect::AppendFloor(amount),
+            UpgradeEffect::AppendElevator(_) => UpgradeEffect::AppendElevator(amount),
+            UpgradeEffect::AddFloorCapacity(_) => UpgradeEffect::AddFloorCapacity(amount),
+            UpgradeEffect::AddElevatorCapacity(_) => UpgradeEffect::AddElevatorCapacity(amount),
+            UpgradeEffect::ElevatorCapacity(_) => UpgradeEffect::ElevatorCapacity(amount),
+            UpgradeEffect::ElevatorSpeed(_) => UpgradeEffect::ElevatorSpeed(amount),
+            UpgradeEffect::ElevatorDwellTime(_) => UpgradeEffect::ElevatorDwellTime(amount),
+            UpgradeEffect::FloorCapacity(_) => UpgradeEffect::FloorCapacity(amount),
+            _ => self.clone()
+        }
+    }
+
+    /// Get a copy of the effect with its factor replaced, for the
+    /// effects which have one
+    pub fn with_factor(&self, factor: f64) -> UpgradeEffect {
+        match self {
+            UpgradeEffect::ElevatorEnergyCoef(_) => UpgradeEffect::ElevatorEnergyCoef(factor),
+            UpgradeEffect::ScaleEnergyUp(_) => UpgradeEffect::ScaleEnergyUp(factor),
+            UpgradeEffect::ScaleEnergyDown(_) => UpgradeEffect::ScaleEnergyDown(factor),
+            UpgradeEffect::ScaleEnergyCoef(_) => UpgradeEffect::ScaleEnergyCoef(factor),
+            _ => self.clone()
+        }
     }
 
-    /// Update the upgrade properties after buying
-    fn buy(&mut self) -> f64 {
-        0.0_f64
+    /// Parse an upgrade effect from a JSON effect object.  Floors and
+    /// elevators are added one at a time unless given an `amount`.
+    pub fn from_json(effect: &JsonValue) -> Result<UpgradeEffect, ElevatorGameInputError> {
+        let effect_type: &str = input::get_str(effect, "type", None)?;
+        match effect_type {
+            "collect_tips" => Ok(UpgradeEffect::CollectTips),
+            "append_floor" => Ok(UpgradeEffect::AppendFloor(input::get_usize(effect, "amount", Some(1_usize))?)),
+            "append_elevator" => Ok(UpgradeEffect::AppendElevator(input::get_usize(effect, "amount", Some(1_usize))?)),
+            "add_floor_capacity" => Ok(UpgradeEffect::AddFloorCapacity(input::get_usize(effect, "amount", None)?)),
+            "add_elevator_capacity" => Ok(UpgradeEffect::AddElevatorCapacity(input::get_usize(effect, "amount", None)?)),
+            "set_controller" => {
+                let controller: &str = input::get_str(effect, "controller", None)?;
+                let kind: ControllerKind = ControllerKind::from_str(controller).ok_or(ElevatorGameInputError::InvalidValue {
+                    key: "controller".to_string(),
+                    value: controller.to_string()
+                })?;
+                Ok(UpgradeEffect::SetController(kind))
+            },
+            "elevator_capacity" => Ok(UpgradeEffect::ElevatorCapacity(input::get_usize(effect, "amount", None)?)),
+            "elevator_speed" => Ok(UpgradeEffect::ElevatorSpeed(input::get_usize(effect, "amount", Some(1_usize))?)),
+            "elevator_energy_coef" => Ok(UpgradeEffect::ElevatorEnergyCoef(get_factor(effect)?)),
+            "elevator_dwell_time" => Ok(UpgradeEffect::ElevatorDwellTime(input::get_usize(effect, "amount", Some(1_usize))?)),
+            "floor_capacity" => Ok(UpgradeEffect::FloorCapacity(input::get_usize(effect, "amount", None)?)),
+            "scale_energy_up" => Ok(UpgradeEffect::ScaleEnergyUp(get_factor(effect)?)),
+            "scale_energy_down" => Ok(UpgradeEffect::ScaleEnergyDown(get_factor(effect)?)),
+            "scale_energy_coef" => Ok(UpgradeEffect::ScaleEnergyCoef(get_factor(effect)?)),
+            _ => Err(ElevatorGameInputError::InvalidValue {
+                key: "type".to_string(),
+                value: effect_type.to_string()
+            })
+        }
+    }
+}
+
+/// Get the factor of an effect from a JSON effect object, making sure
+/// it is finite and positive so that the scaled stat keeps its sign
+pub fn get_factor(effect: &JsonValue) -> Result<f64, ElevatorGameInputError> {
+    let factor: f64 = input::get_f64(effect, "factor", None)?;
+    if !factor.is_finite() || factor <= 0.0_f64 {
+        return Err(ElevatorGameInputError::InvalidValue {
+            key: "factor".to_string(),
+            value: factor.to_string()
+        });
     }
+    Ok(factor)
 }
 
-/// # `AppendFloorUpgrade` struct
+/// # `Prerequisite` enum
 ///
-/// The `AppendFloorUpgrade` struct is an upgrade for adding a floor
-/// to the player's building.  It is constantly available throughout
-/// the game.
-pub struct AppendFloorUpgrade {
-    base_cost: f64,
-    base_coef: f64,
-    num_buys: usize,
-    max_buys: usize,
-    name: String,
-    description: String
+/// The `Prerequisite` enum describes a condition which must be met
+/// before an upgrade unlocks.
+///
+/// - `MinFloors`: the building has at least this many floors
+/// - `MinElevators`: the building has at least this many elevators
+/// - `MinAvgWaitTime`: the building's average wait time is at least
+///   this long, so that smarter dispatch is only offered once the
+///   people in the building start to feel the wait
+/// - `Upgrade`: another upgrade has been bought at least this many
+///   times
+#[derive(Clone, PartialEq, Debug)]
+pub enum Prerequisite {
+    MinFloors(usize),
+    MinElevators(usize),
+    MinAvgWaitTime(f64),
+    Upgrade { id: String, num_buys: usize }
 }
 
-impl AppendFloorUpgrade {
-    /// Initialize an `AppendFloorUpgrade` struct
-    pub fn new(base_cost: f64, base_coef: f64) -> AppendFloorUpgrade {
-        //Set the name of the upgrade and the description
-        let name = "Add Floor".to_string();
-        let description = "Adds a new floor to your building".to_string();
-
-        //Initialize and return the AppendFloorUpgrade
-        AppendFloorUpgrade {
-            base_cost: base_cost,
-            base_coef: base_coef,
-            num_buys: 0_usize,
-            max_buys: usize::MAX,
-            name: name,
-            description: description
+impl Prerequisite {
+    /// Check whether the prerequisite is met by the given building and
+    /// the upgrades bought so far
+    pub fn is_met(&self, building: &Building, upgrades: &ElevatorGameUpgrades) -> bool {
+        match self {
+            Prerequisite::MinFloors(num_floors) => building.floors.len() >= *num_floors,
+            Prerequisite::MinElevators(num_elevators) => building.elevators.len() >= *num_elevators,
+            Prerequisite::MinAvgWaitTime(wait_time) => building.avg_wait_time >= *wait_time,
+            Prerequisite::Upgrade { id, num_buys } => match upgrades.get(id) {
+                Some(upgrade) => upgrade.get_num_buys() >= *num_buys,
+                None => false
+            }
         }
     }
-}
 
-impl ElevatorGameUpgrade for AppendFloorUpgrade {
-    /// Get the cost of the upgrade
-    fn get_cost(&self) -> f64 {
-        self.base_cost + f64::powf(self.base_coef, self.num_buys as f64)
+    /// Serialize the prerequisite into a JSON prerequisite object
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            Prerequisite::MinFloors(num_floors) => object!{
+                type: "min_floors",
+                count: *num_floors
+            },
+            Prerequisite::MinElevators(num_elevators) => object!{
+                type: "min_elevators",
+                count: *num_elevators
+            },
+            Prerequisite::MinAvgWaitTime(wait_time) => object!{
+                type: "min_avg_wait_time",
+                wait_time: *wait_time
+            },
+            Prerequisite::Upgrade { id, num_buys } => object!{
+                type: "upgrade",
+                id: id.as_str(),
+                num_buys: *num_buys
+            }
+        }
     }
 
-    /// Check if the given amount is less than the cost of the upgrade
-    fn is_enough(&self, money: f64) -> bool {
-        money >= self.base_cost + f64::powf(self.base_coef, self.num_buys as f64)
+    /// Parse a prerequisite from a JSON prerequisite object.  An upgrade
+    /// prerequisite without a `num_buys` needs a single buy.
+    pub fn from_json(prerequisite: &JsonValue) -> Result<Prerequisite, ElevatorGameInputError> {
+        let prerequisite_type: &str = input::get_str(prerequisite, "type", None)?;
+        match prerequisite_type {
+            "min_floors" => Ok(Prerequisite::MinFloors(input::get_usize(prerequisite, "count", None)?)),
+            "min_elevators" => Ok(Prerequisite::MinElevators(input::get_usize(prerequisite, "count", None)?)),
+            "min_avg_wait_time" => Ok(Prerequisite::MinAvgWaitTime(input::get_f64(prerequisite, "wait_time", None)?)),
+            "upgrade" => Ok(Prerequisite::Upgrade {
+                id: input::get_str(prerequisite, "id", None)?.to_string(),
+                num_buys: input::get_usize(prerequisite, "num_buys", Some(1_usize))?
+            }),
+            _ => Err(ElevatorGameInputError::InvalidValue {
+                key: "type".to_string(),
+                value: prerequisite_type.to_string()
+            })
+        }
     }
 
-    /// Get the maximum number of floors one can buy
-    fn get_max_buys(&self) -> usize {
-        self.max_buys
+    /// Parse a list of prerequisites from a JSON array.  A missing list
+    /// has no prerequisites.
+    pub fn from_json_array(prerequisites: &JsonValue) -> Result<Vec<Prerequisite>, ElevatorGameInputError> {
+        if prerequisites.is_null() {
+            return Ok(Vec::new());
+        }
+        if !prerequisites.is_array() {
+            return Err(ElevatorGameInputError::WrongType {
+                key: "prerequisites".to_string(),
+                expected: "array".to_string()
+            });
+        }
+        prerequisites.members().map(Prerequisite::from_json).collect()
     }
+}
 
-    /// Get the name of the upgrade
-    fn get_name(&self) -> &str {
-        &self.name
+/// # `UpgradeDefinition` struct
+///
+/// The `UpgradeDefinition` struct describes a shop item: its id, how
+/// it is presented, how it is priced, how many times it can be bought,
+/// what it does and what it takes to unlock it.
+#[derive(Clone)]
+pub struct UpgradeDefinition {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub price: CostCurve,
+    pub max_buys: usize,
+    pub effect: UpgradeEffect,
+    pub prerequisites: Vec<Prerequisite>
+}
+
+impl UpgradeDefinition {
+    /// Initialize an `UpgradeDefinition` struct explicitly
+    pub fn new(id: &str, name: &str, description: &str, price: CostCurve,
+               max_buys: usize, effect: UpgradeEffect,
+               prerequisites: Vec<Prerequisite>) -> UpgradeDefinition {
+        UpgradeDefinition {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            price: price,
+            max_buys: max_buys,
+            effect: effect,
+            prerequisites: prerequisites
+        }
     }
 
-    /// Get the description of the upgrade
-    fn get_description(&self) -> &str {
-        &self.description
+    /// Get the default upgrade definitions.  Elevators unlock once the
+    /// building has a few floors, and each smarter controller unlocks
+    /// once the one before it is bought and the building is big and
+    /// busy enough to need it.
+    pub fn defaults() -> Vec<UpgradeDefinition> {
+        vec![
+            UpgradeDefinition::new(
+                "collect_tips", "Collect Tips", "Collect the tips accumulated by your building",
+                CostCurve::Free, usize::MAX, UpgradeEffect::CollectTips, Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "append_floor", "Add Floor", "Adds a new floor to your building",
+                CostCurve::BasePlusPower { base_cost: 10_f64, base_coef: 1.5_f64 },
+                usize::MAX, UpgradeEffect::AppendFloor(1_usize), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "append_elevator", "Add Elevator", "Adds a new elevator to your building",
+                CostCurve::BasePlusPower { base_cost: 100_f64, base_coef: 1.9_f64 },
+                usize::MAX, UpgradeEffect::AppendElevator(1_usize),
+                vec![Prerequisite::MinFloors(3_usize)]
+            ),
+            UpgradeDefinition::new(
+                "add_floor_capacity", "Add Floor Capacity", "Adds more capacity to your floors",
+                CostCurve::BasePlusPower { base_cost: 10_f64, base_coef: 1.1_f64 },
+                usize::MAX, UpgradeEffect::AddFloorCapacity(100_usize), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "add_elevator_capacity", "Add Elevator Capacity", "Adds more capacity to your elevators",
+                CostCurve::BasePlusPower { base_cost: 10_f64, base_coef: 1.1_f64 },
+                usize::MAX, UpgradeEffect::AddElevatorCapacity(10_usize), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "random_controller", "Automatic Dispatch", "Moves your elevators on their own, at random",
+                CostCurve::Fixed(50_f64), 1_usize, UpgradeEffect::SetController(ControllerKind::Random), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "round_robin_controller", "Round Robin Dispatch", "Assigns each new call to your elevators in turn",
+                CostCurve::Fixed(250_f64), 1_usize, UpgradeEffect::SetController(ControllerKind::RoundRobin),
+                vec![Prerequisite::MinElevators(2_usize)]
+            ),
+            UpgradeDefinition::new(
+                "nearest_controller", "Nearest Car Dispatch", "Sends each elevator to the nearest request",
+                CostCurve::Fixed(500_f64), 1_usize, UpgradeEffect::SetController(ControllerKind::Nearest),
+                vec![Prerequisite::Upgrade { id: "round_robin_controller".to_string(), num_buys: 1_usize }]
+            ),
+            UpgradeDefinition::new(
+                "look_controller", "LOOK Dispatch", "Sweeps each elevator up and down, serving every request on the way",
+                CostCurve::Fixed(2000_f64), 1_usize, UpgradeEffect::SetController(ControllerKind::Look),
+                vec![
+                    Prerequisite::Upgrade { id: "nearest_controller".to_string(), num_buys: 1_usize },
+                    Prerequisite::MinElevators(3_usize)
+                ]
+            ),
+            UpgradeDefinition::new(
+                "collective_controller", "Collective Control", "Sweeps each elevator, stopping only for people headed its way",
+                CostCurve::Fixed(5000_f64), 1_usize, UpgradeEffect::SetController(ControllerKind::Collective),
+                vec![
+                    Prerequisite::Upgrade { id: "look_controller".to_string(), num_buys: 1_usize },
+                    Prerequisite::MinElevators(5_usize),
+                    Prerequisite::MinAvgWaitTime(10_f64)
+                ]
+            ),
+            UpgradeDefinition::new(
+                "elevator_capacity", "Bigger Car", "Adds more capacity to one elevator",
+                CostCurve::Exponential { base_cost: 15_f64, base_coef: 1.15_f64 },
+                usize::MAX, UpgradeEffect::ElevatorCapacity(5_usize), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "elevator_speed", "Faster Motor", "Lets one elevator pass an extra floor with no stops each step",
+                CostCurve::Exponential { base_cost: 200_f64, base_coef: 2.5_f64 },
+                3_usize, UpgradeEffect::ElevatorSpeed(1_usize),
+                vec![Prerequisite::MinFloors(6_usize)]
+            ),
+            UpgradeDefinition::new(
+                "elevator_efficiency", "Efficient Drive", "Lowers the energy one elevator spends per rider",
+                CostCurve::Exponential { base_cost: 50_f64, base_coef: 1.3_f64 },
+                10_usize, UpgradeEffect::ElevatorEnergyCoef(0.9_f64), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "elevator_dwell_time", "Quick Doors", "Shortens the time one elevator holds its doors open",
+                CostCurve::Fixed(150_f64), 1_usize, UpgradeEffect::ElevatorDwellTime(1_usize), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "floor_capacity", "Roomier Floor", "Adds more capacity to one floor",
+                CostCurve::Exponential { base_cost: 10_f64, base_coef: 1.15_f64 },
+                usize::MAX, UpgradeEffect::FloorCapacity(25_usize), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "efficient_motors", "Efficient Motors", "Lowers the energy every elevator spends moving up",
+                CostCurve::Exponential { base_cost: 100_f64, base_coef: 1.6_f64 },
+                10_usize, UpgradeEffect::ScaleEnergyUp(0.9_f64), Vec::new()
+            ),
+            UpgradeDefinition::new(
+                "counterweights", "Counterweights", "Lowers the energy every elevator spends per rider",
+                CostCurve::Exponential { base_cost: 250_f64, base_coef: 1.8_f64 },
+                5_usize, UpgradeEffect::ScaleEnergyCoef(0.85_f64),
+                vec![Prerequisite::Upgrade { id: "efficient_motors".to_string(), num_buys: 1_usize }]
+            ),
+            UpgradeDefinition::new(
+                "regenerative_braking", "Regenerative Braking", "Lowers the energy every elevator spends moving down",
+                CostCurve::Exponential { base_cost: 500_f64, base_coef: 2_f64 },
+                5_usize, UpgradeEffect::ScaleEnergyDown(0.8_f64),
+                vec![Prerequisite::Upgrade { id: "counterweights".to_string(), num_buys: 1_usize }]
+            )
+        ]
     }
 
-    /// Update the upgrade properties after buying
-    fn buy(&mut self) -> f64 {
-        //Make sure the upgrade can be purchased
-        if self.num_buys > self.max_buys {
-            panic!("Cannot buy upgrade: {}", self.name);
-        }
+    /// Parse an upgrade definition from a JSON definition object.  An
+    /// upgrade without a `max_buys` can be bought without limit, and an
+    /// upgrade without `prerequisites` is unlocked from the start.
+    ///
+    /// ## Example
+    ///
+    /// ```json
+    /// {
+    ///     "id": "add_floor_capacity",
+    ///     "name": "Add Floor Capacity",
+    ///     "description": "Adds more capacity to your floors",
+    ///     "price": { "type": "base_plus_power", "base_cost": 10.0, "base_coef": 1.1 },
+    ///     "effect": { "type": "add_floor_capacity", "amount": 100 },
+    ///     "prerequisites": [{ "type": "min_floors", "count": 5 }]
+    /// }
+    /// ```
+    pub fn from_json(definition: &JsonValue) -> Result<UpgradeDefinition, ElevatorGameInputError> {
+        Ok(UpgradeDefinition {
+            id: input::get_str(definition, "id", None)?.to_string(),
+            name: input::get_str(definition, "name", None)?.to_string(),
+            description: input::get_str(definition, "description", Some(""))?.to_string(),
+            price: CostCurve::from_json(&definition["price"])?,
+            max_buys: input::get_usize(definition, "max_buys", Some(usize::MAX))?,
+            effect: UpgradeEffect::from_json(&definition["effect"])?,
+            prerequisites: Prerequisite::from_json_array(&definition["prerequisites"])?
+        })
+    }
 
-        //Calculate the cost before incrementing the num buys
-        let cost: f64 = self.base_cost + f64::powf(self.base_coef, self.num_buys as f64);
+    /// Parse a list of upgrade definitions from a JSON array
+    pub fn from_json_array(definitions: &JsonValue) -> Result<Vec<UpgradeDefinition>, ElevatorGameInputError> {
+        //Make sure the definitions are an array
+        if !definitions.is_array() {
+            return Err(ElevatorGameInputError::WrongType {
+                key: "upgrades".to_string(),
+                expected: "array".to_string()
+            });
+        }
 
-        //If it can be purchased, then update the number of buys
-        self.num_buys += 1;
+        //Parse each definition, making sure the ids are unique
+        let mut parsed: Vec<UpgradeDefinition> = Vec::new();
+        for definition in definitions.members() {
+            let definition: UpgradeDefinition = UpgradeDefinition::from_json(definition)?;
+            if parsed.iter().any(|other| other.id == definition.id) {
+                return Err(ElevatorGameInputError::InvalidValue {
+                    key: "id".to_string(),
+                    value: definition.id
+                });
+            }
+            parsed.push(definition);
+        }
+        UpgradeDefinition::check_prerequisites(&parsed)?;
+        Ok(parsed)
+    }
 
-        //Return the cost
-        cost
+    /// Make sure every upgrade prerequisite in a list of upgrade
+    /// definitions names an upgrade in the list
+    pub fn check_prerequisites(definitions: &[UpgradeDefinition]) -> Result<(), ElevatorGameInputError> {
+        for definition in definitions.iter() {
+            for prerequisite in definition.prerequisites.iter() {
+                if let Prerequisite::Upgrade { id, .. } = prerequisite {
+                    if !definitions.iter().any(|other| &other.id == id) {
+                        return Err(ElevatorGameInputError::InvalidValue {
+                            key: "prerequisites".to_string(),
+                            value: id.to_string()
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 }
 
-/// # `AppendElevatorUpgrade` struct
+/// # `Upgrade` struct
 ///
-/// The `AppendElevatorUpgrade` struct is an upgrade for adding an elevator
-/// to the player's building.  It is constantly available throughout
-/// the game.
-pub struct AppendElevatorUpgrade {
-    base_cost: f64,
-    base_coef: f64,
+/// The `Upgrade` struct is an entry in the upgrade registry.  It pairs
+/// an `UpgradeDefinition` with the number of times it has been bought
+/// and whether it has been unlocked.  Upgrades bought for a single
+/// elevator or floor also track how many times they were bought for
+/// each one.
+pub struct Upgrade {
+    definition: UpgradeDefinition,
     num_buys: usize,
-    max_buys: usize,
-    name: String,
-    description: String
+    target_buys: Vec<usize>,
+    unlocked: bool
 }
 
-impl AppendElevatorUpgrade {
-    /// Initialize an `AppendElevatorUpgrade` struct
-    pub fn new(base_cost: f64, base_coef: f64) -> AppendElevatorUpgrade {
-        //Set the name of the upgrade and the description
-        let name = "Add Elevator".to_string();
-        let description = "Adds a new elevator to your building".to_string();
-
-        //Initialize and return the AppendElevatorUpgrade
-        AppendElevatorUpgrade {
-            base_cost: base_cost,
-            base_coef: base_coef,
+impl Upgrade {
+    /// Initialize an `Upgrade` struct which has not been bought yet,
+    /// unlocked only if it has no prerequisites
+    pub fn new(definition: UpgradeDefinition) -> Upgrade {
+        let unlocked: bool = definition.prerequisites.is_empty();
+        Upgrade {
+            definition: definition,
             num_buys: 0_usize,
-            max_buys: usize::MAX,
-            name: name,
-            description: description
+            target_buys: Vec::new(),
+            unlocked: unlocked
         }
     }
-}
 
-impl ElevatorGameUpgrade for AppendElevatorUpgrade {
-    /// Get the cost of the upgrade
-    fn get_cost(&self) -> f64 {
-        self.base_cost + f64::powf(self.base_coef, self.num_buys as f64)
+    /// Get the id of the upgrade
+    pub fn get_id(&self) -> &str {
+        &self.definition.id
     }
 
-    /// Check if the given amount is less than the cost of the upgrade
-    fn is_enough(&self, money: f64) -> bool {
-        money >= self.base_cost + f64::powf(self.base_coef, self.num_buys as f64)
+    /// Get the effect of the upgrade
+    pub fn get_effect(&self) -> &UpgradeEffect {
+        &self.definition.effect
     }
 
-    /// Get the maximum number of floors one can buy
-    fn get_max_buys(&self) -> usize {
-        self.max_buys
+    /// Get the prerequisites of the upgrade
+    pub fn get_prerequisites(&self) -> &[Prerequisite] {
+        &self.definition.prerequisites
     }
 
-    /// Get the name of the upgrade
-    fn get_name(&self) -> &str {
-        &self.name
+    /// Set whether the upgrade has been unlocked
+    pub fn set_unlocked(&mut self, unlocked: bool) {
+        self.unlocked = unlocked;
     }
 
-    /// Get the description of the upgrade
-    fn get_description(&self) -> &str {
-        &self.description
+    /// Get the number of times the upgrade has been bought for the
+    /// target at the given index
+    pub fn get_target_buys(&self, index: usize) -> usize {
+        self.target_buys.get(index).copied().unwrap_or(0_usize)
     }
 
-    /// Update the upgrade properties after buying
-    fn buy(&mut self) -> f64 {
-        //Make sure the upgrade can be purchased
-        if self.num_buys > self.max_buys {
-            panic!("Cannot buy upgrade: {}", self.name);
-        }
-
-        //Calculate the cost before incrementing the num buys
-        let cost: f64 = self.base_cost + f64::powf(self.base_coef, self.num_buys as f64);
-
-        //If it can be purchased, then update the number of buys
-        self.num_buys += 1;
-
-        //Return the cost
-        cost
+    /// Get the number of times the upgrade has been bought for each
+    /// target, by index
+    pub fn get_all_target_buys(&self) -> &[usize] {
+        &self.target_buys
     }
-}
 
-/// # `AddFloorCapacityUpgrade` struct
-///
-/// The `AddFloorCapacityUpgrade` struct is an upgrade for adding capacity
-/// to the floors of the player's buildings.  It is constantly available
-/// throughout the game.
-pub struct AddFloorCapacityUpgrade {
-    base_cost: f64,
-    base_coef: f64,
-    num_buys: usize,
-    max_buys: usize,
-    name: String,
-    description: String
-}
+    /// Set the number of times the upgrade has been bought for each
+    /// target, by index
+    pub fn set_all_target_buys(&mut self, target_buys: Vec<usize>) {
+        self.target_buys = target_buys;
+    }
 
-impl AddFloorCapacityUpgrade {
-    /// Initialize an `AddFloorCapacityUpgrade` struct
-    pub fn new(base_cost: f64, base_coef: f64) -> AddFloorCapacityUpgrade {
-        //Set the name of the upgrade and the description
-        let name = "Add Floor Capacity".to_string();
-        let description = "Adds more capacity to your floors".to_string();
-
-        //Initialize and return the AddFloorCapacityUpgrade
-        AddFloorCapacityUpgrade {
-            base_cost: base_cost,
-            base_coef: base_coef,
-            num_buys: 0_usize,
-            max_buys: usize::MAX,
-            name: name,
-            description: description
+    /// Get a view of the upgrade as bought for the target at the given
+    /// index, priced by the number of times it was bought for it
+    pub fn for_target(&mut self, index: usize) -> TargetedUpgrade<'_> {
+        TargetedUpgrade {
+            upgrade: self,
+            index: index
         }
     }
 }
 
-impl ElevatorGameUpgrade for AddFloorCapacityUpgrade {
+impl ElevatorGameUpgrade for Upgrade {
     /// Get the cost of the upgrade
     fn get_cost(&self) -> f64 {
-        self.base_cost + f64::powf(self.base_coef, self.num_buys as f64)
+        self.definition.price.get_cost(self.num_buys)
     }
 
-    /// Check if the given amount is less than the cost of the upgrade
-    fn is_enough(&self, money: f64) -> bool {
-        money >= self.base_cost + f64::powf(self.base_coef, self.num_buys as f64)
+    /// Get the cost of the upgrade `n` buys from now, so that
+    /// `cost_of_next(0)` is the current cost
+    fn cost_of_next(&self, n: usize) -> f64 {
+        self.definition.price.get_cost(self.num_buys.saturating_add(n))
     }
 
-    /// Get the maximum number of floors one can buy
+    /// Get the maximum number of times the upgrade can be bought
     fn get_max_buys(&self) -> usize {
-        self.max_buys
+        self.definition.max_buys
+    }
+
+    /// Get the number of times the upgrade has been bought
+    fn get_num_buys(&self) -> usize {
+        self.num_buys
+    }
+
+    /// Set the number of times the upgrade has been bought
+    fn set_num_buys(&mut self, num_buys: usize) {
+        self.num_buys = num_buys;
     }
 
     /// Get the name of the upgrade
     fn get_name(&self) -> &str {
-        &self.name
+        &self.definition.name
     }
 
     /// Get the description of the upgrade
     fn get_description(&self) -> &str {
-        &self.description
+        &self.definition.description
+    }
+
+    /// Check whether the upgrade has been unlocked.  An upgrade which
+    /// has been bought is always unlocked.
+    fn is_unlocked(&self) -> bool {
+        self.unlocked || self.num_buys > 0_usize
+    }
+
+    /// Get the scope of the upgrade
+    fn get_scope(&self) -> UpgradeScope {
+        self.definition.effect.get_scope()
+    }
+
+    /// Apply the effect of the upgrade to the building and the rest of
+    /// the game
+    fn apply(&self, building: &mut Building, context: &mut GameContext) {
+        self.definition.effect.apply(building, context);
     }
 
-    /// Update the upgrade properties after buying
-    fn buy(&mut self) -> f64 {
+    /// Update the upgrade properties after buying, returning the cost
+    /// of the buy or `MaxedOut` if it cannot be bought any more times
+    fn buy(&mut self) -> Result<f64, PurchaseError> {
         //Make sure the upgrade can be purchased
-        if self.num_buys > self.max_buys {
-            panic!("Cannot buy upgrade: {}", self.name);
+        if self.num_buys >= self.definition.max_buys {
+            return Err(PurchaseError::MaxedOut);
         }
 
         //Calculate the cost before incrementing the num buys
-        let cost: f64 = self.base_cost + f64::powf(self.base_coef, self.num_buys as f64);
+        let cost: f64 = self.get_cost();
 
         //If it can be purchased, then update the number of buys
         self.num_buys += 1;
 
         //Return the cost
-        cost
+        Ok(cost)
     }
 }
 
-/// # `AddElevatorCapacityUpgrade` struct
+/// # `TargetedUpgrade` struct
 ///
-/// The `AddElevatorCapacityUpgrade` struct is an upgrade for adding capacity
-/// to the floors of the player's buildings.  It is constantly available
-/// throughout the game.
-pub struct AddElevatorCapacityUpgrade {
-    base_cost: f64,
-    base_coef: f64,
-    num_buys: usize,
-    max_buys: usize,
-    name: String,
-    description: String
-}
-
-impl AddElevatorCapacityUpgrade {
-    /// Initialize an `AddElevatorCapacityUpgrade` struct
-    pub fn new(base_cost: f64, base_coef: f64) -> AddElevatorCapacityUpgrade {
-        //Set the name of the upgrade and the description
-        let name = "Add Elevator Capacity".to_string();
-        let description = "Adds more capacity to your elevators".to_string();
-
-        //Initialize and return the AddElevatorCapacityUpgrade
-        AddElevatorCapacityUpgrade {
-            base_cost: base_cost,
-            base_coef: base_coef,
-            num_buys: 0_usize,
-            max_buys: usize::MAX,
-            name: name,
-            description: description
-        }
-    }
+/// The `TargetedUpgrade` struct is a view of an `Upgrade` bought for a
+/// single elevator or floor.  It is priced and limited by the number of
+/// times the upgrade was bought for that elevator or floor, so that
+/// each one levels up independently.
+pub struct TargetedUpgrade<'a> {
+    upgrade: &'a mut Upgrade,
+    index: usize
 }
 
-impl ElevatorGameUpgrade for AddElevatorCapacityUpgrade {
-    /// Get the cost of the upgrade
+impl ElevatorGameUpgrade for TargetedUpgrade<'_> {
+    /// Get the cost of the upgrade for the target
     fn get_cost(&self) -> f64 {
-        self.base_cost + f64::powf(self.base_coef, self.num_buys as f64)
+        self.cost_of_next(0_usize)
     }
 
-    /// Check if the given amount is less than the cost of the upgrade
-    fn is_enough(&self, money: f64) -> bool {
-        money >= self.base_cost + f64::powf(self.base_coef, self.num_buys as f64)
+    /// Get the cost of the upgrade for the target `n` buys from now
+    fn cost_of_next(&self, n: usize) -> f64 {
+        self.upgrade.definition.price.get_cost(self.get_num_buys().saturating_add(n))
     }
 
-    /// Get the maximum number of floors one can buy
+    /// Get the maximum number of times the upgrade can be bought for
+    /// the target
     fn get_max_buys(&self) -> usize {
-        self.max_buys
+        self.upgrade.definition.max_buys
+    }
+
+    /// Get the number of times the upgrade has been bought for the
+    /// target
+    fn get_num_buys(&self) -> usize {
+        self.upgrade.get_target_buys(self.index)
+    }
+
+    /// Set the number of times the upgrade has been bought for the
+    /// target
+    fn set_num_buys(&mut self, num_buys: usize) {
+        if self.upgrade.target_buys.len() <= self.index {
+            self.upgrade.target_buys.resize(self.index + 1_usize, 0_usize);
+        }
+        self.upgrade.target_buys[self.index] = num_buys;
     }
 
     /// Get the name of the upgrade
     fn get_name(&self) -> &str {
-        &self.name
+        self.upgrade.get_name()
     }
 
     /// Get the description of the upgrade
     fn get_description(&self) -> &str {
-        &self.description
+        self.upgrade.get_description()
     }
 
-    /// Update the upgrade properties after buying
-    fn buy(&mut self) -> f64 {
-        //Make sure the upgrade can be purchased
-        if self.num_buys > self.max_buys {
-            panic!("Cannot buy upgrade: {}", self.name);
-        }
+    /// Check whether the upgrade has been unlocked
+    fn is_unlocked(&self) -> bool {
+        self.upgrade.is_unlocked()
+    }
 
-        //Calculate the cost before incrementing the num buys
-        let cost: f64 = self.base_cost + f64::powf(self.base_coef, self.num_buys as f64);
+    /// Get the scope of the upgrade
+    fn get_scope(&self) -> UpgradeScope {
+        self.upgrade.get_scope()
+    }
 
-        //If it can be purchased, then update the number of buys
-        self.num_buys += 1;
+    /// Apply the effect of the upgrade to the building and the rest of
+    /// the game
+    fn apply(&self, building: &mut Building, context: &mut GameContext) {
+        self.upgrade.apply(building, context);
+    }
 
-        //Return the cost
-        cost
+    /// Update the upgrade properties after buying it for the target,
+    /// returning the cost of the buy or `MaxedOut` if it cannot be
+    /// bought for the target any more times
+    fn buy(&mut self) -> Result<f64, PurchaseError> {
+        //Make sure the upgrade can be purchased for the target
+        let num_buys: usize = self.get_num_buys();
+        if num_buys >= self.get_max_buys() {
+            return Err(PurchaseError::MaxedOut);
+        }
+
+        //Calculate the cost, then count the buy both for the target and
+        //towards the upgrade as a whole
+        let cost: f64 = self.get_cost();
+        self.set_num_buys(num_buys + 1_usize);
+        self.upgrade.num_buys += 1_usize;
+        Ok(cost)
     }
 }
\ No newline at end of file